@@ -23,7 +23,7 @@ fn main() {
 }
 
 fn setup(mut asset_events: EventWriter<LoadScript>) {
-    asset_events.send(LoadScript::new("sword.json"));
+    asset_events.send(LoadScript::new("sword.script.json"));
 }
 
 fn spawn_sword(mut commands: Commands, mut events: EventReader<ScriptsReady>) {
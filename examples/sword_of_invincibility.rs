@@ -42,7 +42,7 @@ fn main() {
 }
 
 fn setup(mut asset_events: EventWriter<LoadScript>) {
-    asset_events.send(LoadScript::new("sword_of_invincibility.json"));
+    asset_events.send(LoadScript::new("sword_of_invincibility.script.json"));
 }
 
 fn spawn_sword(mut commands: Commands, mut events: EventReader<ScriptsReady>) {
@@ -3,7 +3,6 @@ use scripting::{
     expr::ExprData, LoadScript, ScriptBundle, ScriptComponent, ScriptPlugin, ScriptsReady,
 };
 
-
 #[derive(Default, Component, Deref, DerefMut)]
 pub struct Durability(f64);
 
@@ -44,16 +43,16 @@ fn main() {
 }
 
 fn setup(mut asset_events: EventWriter<LoadScript>) {
-    asset_events.send(LoadScript::new("sword_of_unbreaking.json"));
+    asset_events.send(LoadScript::new("sword_of_unbreaking.script.json"));
 }
 
 fn spawn_sword(mut commands: Commands, mut events: EventReader<ScriptsReady>) {
     for _event in events.read() {
         commands.spawn((
-            Durability(0.1),
-            MaxDurability(1.),
             OnEquip,
-            ScriptBundle::new("sword_of_unbreaking"),
+            ScriptBundle::new("sword_of_unbreaking")
+                .with_override("durability", 0.1)
+                .with_override("max_durability", 1.),
         ));
     }
 }
@@ -0,0 +1,328 @@
+use crate::{
+    hierarchy::ChildData, AssetRegistry, Capabilities, CapabilityError, ComponentsData, LoadTicket,
+    Registry, ResolveError,
+};
+use bevy::{
+    asset::{io::Reader, Asset, AssetLoader, Assets, AsyncReadExt, LoadContext},
+    ecs::event::Event,
+    reflect::TypePath,
+    utils::BoxedFuture,
+};
+use serde::{Deserialize, Serialize};
+use serde_json::{Map, Number, Value};
+use std::{fmt, path::PathBuf};
+
+#[derive(Debug)]
+pub enum CacheError {
+    Resolve(String, ResolveError),
+    Unregistered(String, CapabilityError),
+    UnknownComponent(String, String),
+    Io(String),
+    Decode(String),
+    Checksum { expected: u64, found: u64 },
+}
+
+impl fmt::Display for CacheError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            CacheError::Resolve(id, error) => write!(f, "Failed to cache `{id}`: {error}"),
+            CacheError::Unregistered(id, error) => {
+                let (kind, name) = match error {
+                    CapabilityError::Function(name) => ("function", name),
+                    CapabilityError::Operation(name) => ("operation", name),
+                    CapabilityError::Component(name) => ("component", name),
+                    CapabilityError::Event(name) => ("event", name),
+                    CapabilityError::Script(name) => ("script", name),
+                    CapabilityError::Target(name) => ("target", name),
+                    CapabilityError::Unknown(name) => ("id", name),
+                    CapabilityError::Invalid(_) => {
+                        return write!(f, "Failed to cache `{id}`: {error}");
+                    }
+                };
+                write!(f, "Failed to cache `{id}`: Unknown {kind} `{name}`.")
+            }
+            CacheError::UnknownComponent(id, component) => {
+                write!(
+                    f,
+                    "Failed to cache `{id}`: Unknown component `{component}`."
+                )
+            }
+            CacheError::Io(error) => write!(f, "Failed to read script cache: {error}"),
+            CacheError::Decode(error) => write!(f, "Failed to decode script cache: {error}"),
+            CacheError::Checksum { expected, found } => write!(
+                f,
+                "Script cache was built for registry {found:016x}, expected {expected:016x}."
+            ),
+        }
+    }
+}
+
+impl std::error::Error for CacheError {}
+
+// Scripts packed after `extends`, packs and migrations are applied. Built expressions are
+// trait objects that can't be written out, so they are still built when the scripts spawn.
+#[derive(Clone, Serialize, Deserialize, Asset, TypePath)]
+pub struct ScriptCache {
+    pub checksum: u64,
+    scripts: Vec<(String, Vec<(String, PackedValue)>)>,
+}
+
+impl ScriptCache {
+    pub fn ids(&self) -> impl Iterator<Item = &str> + '_ {
+        self.scripts.iter().map(|(id, _)| id.as_str())
+    }
+
+    pub fn to_bytes(&self) -> Vec<u8> {
+        bincode::serialize(self).unwrap()
+    }
+
+    pub fn from_bytes(bytes: &[u8]) -> Result<Self, CacheError> {
+        bincode::deserialize(bytes).map_err(|error| CacheError::Decode(error.to_string()))
+    }
+
+    pub(crate) fn scripts(&self) -> impl Iterator<Item = (String, ComponentsData)> + '_ {
+        self.scripts.iter().map(|(id, values)| {
+            let values = values
+                .iter()
+                .map(|(key, value)| (key.clone(), value.clone().into()))
+                .collect();
+            (id.clone(), ComponentsData(values))
+        })
+    }
+}
+
+// `serde_json::Value` needs a self-describing format, so bincode gets a tagged copy.
+#[derive(Clone, Serialize, Deserialize)]
+enum PackedValue {
+    Null,
+    Bool(bool),
+    Int(i64),
+    UInt(u64),
+    Float(f64),
+    String(String),
+    Array(Vec<PackedValue>),
+    Object(Vec<(String, PackedValue)>),
+}
+
+impl From<Value> for PackedValue {
+    fn from(value: Value) -> Self {
+        match value {
+            Value::Null => PackedValue::Null,
+            Value::Bool(value) => PackedValue::Bool(value),
+            Value::Number(number) => {
+                if let Some(value) = number.as_u64() {
+                    PackedValue::UInt(value)
+                } else if let Some(value) = number.as_i64() {
+                    PackedValue::Int(value)
+                } else {
+                    PackedValue::Float(number.as_f64().unwrap())
+                }
+            }
+            Value::String(value) => PackedValue::String(value),
+            Value::Array(items) => PackedValue::Array(items.into_iter().map(Into::into).collect()),
+            Value::Object(map) => PackedValue::Object(
+                map.into_iter()
+                    .map(|(key, value)| (key, value.into()))
+                    .collect(),
+            ),
+        }
+    }
+}
+
+impl From<PackedValue> for Value {
+    fn from(value: PackedValue) -> Self {
+        match value {
+            PackedValue::Null => Value::Null,
+            PackedValue::Bool(value) => Value::Bool(value),
+            PackedValue::Int(value) => Value::from(value),
+            PackedValue::UInt(value) => Value::from(value),
+            PackedValue::Float(value) => Number::from_f64(value).map_or(Value::Null, Value::Number),
+            PackedValue::String(value) => Value::String(value),
+            PackedValue::Array(items) => Value::Array(items.into_iter().map(Into::into).collect()),
+            PackedValue::Object(entries) => Value::Object(
+                entries
+                    .into_iter()
+                    .map(|(key, value)| (key, value.into()))
+                    .collect::<Map<_, _>>(),
+            ),
+        }
+    }
+}
+
+impl Registry {
+    pub fn checksum(&self) -> u64 {
+        let mut ids: Vec<_> = self
+            .spawn_fns
+            .keys()
+            .map(|id| format!("c:{id}"))
+            .chain(self.fns.keys().map(|id| format!("f:{id}")))
+            .chain(self.operations.keys().map(|id| format!("o:{id}")))
+            .chain(self.send_fns.keys().map(|id| format!("e:{id}")))
+            .collect();
+        ids.sort();
+
+        // FNV-1a, which stays stable across builds unlike the std hasher.
+        let mut hash: u64 = 0xcbf29ce484222325;
+        for byte in ids.join("\0").bytes() {
+            hash ^= u64::from(byte);
+            hash = hash.wrapping_mul(0x100000001b3);
+        }
+        hash
+    }
+}
+
+impl AssetRegistry {
+    pub fn build_cache(
+        &mut self,
+        registry: &Registry,
+        assets: &Assets<ComponentsData>,
+    ) -> Result<ScriptCache, CacheError> {
+        let mut ids: Vec<_> = self.all_handles().map(|(id, _)| id.clone()).collect();
+        ids.sort();
+        ids.dedup();
+
+        // Only what the registry knows about is limited, scripts can spawn any other script.
+        let registered = Capabilities::all()
+            .with_functions(registry.fns.keys().cloned())
+            .with_operations(registry.operations.keys().cloned())
+            .with_components(registry.spawn_fns.keys().cloned());
+
+        let mut scripts = Vec::new();
+        for id in ids {
+            let data = self
+                .resolve(&id, assets)
+                .map_err(|error| CacheError::Resolve(id.clone(), error))?;
+
+            if let Some(component) = unknown_component(registry, &data) {
+                return Err(CacheError::UnknownComponent(id, component));
+            }
+            registry
+                .check(&data, &registered)
+                .map_err(|error| CacheError::Unregistered(id.clone(), error))?;
+
+            let mut values: Vec<_> = data
+                .0
+                .into_iter()
+                .map(|(key, value)| (key, value.into()))
+                .collect();
+            values.sort_by(|(a, _), (b, _)| a.cmp(b));
+            scripts.push((id, values));
+        }
+
+        Ok(ScriptCache {
+            checksum: registry.checksum(),
+            scripts,
+        })
+    }
+}
+
+fn unknown_component(registry: &Registry, data: &ComponentsData) -> Option<String> {
+    data.0.iter().find_map(|(key, value)| match key.as_str() {
+        "params" => None,
+        "children" => serde_json::from_value::<Vec<ChildData>>(value.clone())
+            .ok()?
+            .into_iter()
+            .find_map(|child| match child {
+                ChildData::Script { overrides, .. } => {
+                    unknown_component(registry, &ComponentsData(overrides))
+                }
+                ChildData::Inline(values) => unknown_component(registry, &ComponentsData(values)),
+            }),
+        _ if registry.spawn_fns.contains_key(key) => None,
+        _ => Some(key.clone()),
+    })
+}
+
+#[derive(Event)]
+pub struct LoadScriptCache {
+    pub(crate) path: PathBuf,
+    pub(crate) ticket: LoadTicket,
+}
+
+impl LoadScriptCache {
+    pub fn new(path: impl Into<PathBuf>) -> Self {
+        Self {
+            path: path.into(),
+            ticket: LoadTicket::next(),
+        }
+    }
+
+    pub fn ticket(&self) -> LoadTicket {
+        self.ticket
+    }
+}
+
+pub(crate) struct ScriptCacheLoader;
+
+impl AssetLoader for ScriptCacheLoader {
+    type Asset = ScriptCache;
+    type Settings = ();
+    type Error = CacheError;
+
+    fn load<'a>(
+        &'a self,
+        reader: &'a mut Reader,
+        _settings: &'a (),
+        _load_context: &'a mut LoadContext,
+    ) -> BoxedFuture<'a, Result<ScriptCache, CacheError>> {
+        Box::pin(async move {
+            let mut bytes = Vec::new();
+            reader
+                .read_to_end(&mut bytes)
+                .await
+                .map_err(|error| CacheError::Io(error.to_string()))?;
+            ScriptCache::from_bytes(&bytes)
+        })
+    }
+
+    fn extensions(&self) -> &[&str] {
+        &["scripts"]
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use serde_json::json;
+
+    #[test]
+    fn round_trip() {
+        let values = json!({
+            "damage": ["+", 1, 2.5],
+            "durability": -3,
+            "on_equip": {"add": "invincible", "after": [0.5, {"remove": null}]},
+            "big": u64::MAX,
+            "flag": true,
+        });
+        let Value::Object(values) = values else {
+            unreachable!()
+        };
+        let cache = ScriptCache {
+            checksum: 42,
+            scripts: vec![(
+                String::from("sword"),
+                values
+                    .clone()
+                    .into_iter()
+                    .map(|(key, value)| (key, value.into()))
+                    .collect(),
+            )],
+        };
+
+        let cache = ScriptCache::from_bytes(&cache.to_bytes()).unwrap();
+        assert_eq!(cache.checksum, 42);
+        assert_eq!(cache.ids().collect::<Vec<_>>(), ["sword"]);
+
+        let (id, data) = cache.scripts().next().unwrap();
+        assert_eq!(id, "sword");
+        assert_eq!(data.0, values.into_iter().collect());
+    }
+
+    #[test]
+    fn decode_error() {
+        assert!(matches!(
+            ScriptCache::from_bytes(&[1, 2, 3]),
+            Err(CacheError::Decode(_))
+        ));
+    }
+}
@@ -0,0 +1,345 @@
+use crate::{expr::function::Args, resolve::merge, ComponentsData, Registry};
+use serde::de::DeserializeOwned;
+use serde_json::{Map, Value};
+use std::{collections::HashSet, fmt};
+
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub enum CapabilityError {
+    Function(String),
+    Operation(String),
+    Component(String),
+    Event(String),
+    Script(String),
+    Target(String),
+    Unknown(String),
+    Invalid(String),
+}
+
+impl CapabilityError {
+    // Prefixes invalid values with where they were found, like "`on_equip`: `add`: ...".
+    pub(crate) fn within(self, id: &str) -> Self {
+        match self {
+            CapabilityError::Invalid(reason) => {
+                CapabilityError::Invalid(format!("`{id}`: {reason}"))
+            }
+            error => error,
+        }
+    }
+}
+
+impl fmt::Display for CapabilityError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            CapabilityError::Function(id) => write!(f, "Function `{id}` is not allowed."),
+            CapabilityError::Operation(id) => write!(f, "Operation `{id}` is not allowed."),
+            CapabilityError::Component(id) => write!(f, "Component `{id}` is not allowed."),
+            CapabilityError::Event(id) => write!(f, "Event `{id}` is not allowed."),
+            CapabilityError::Script(id) => write!(f, "Script `{id}` is not allowed."),
+            CapabilityError::Target(id) => write!(f, "Target `{id}` is not allowed."),
+            CapabilityError::Unknown(id) => write!(f, "`{id}` is not registered."),
+            CapabilityError::Invalid(reason) => write!(f, "Invalid value for {reason}"),
+        }
+    }
+}
+
+impl std::error::Error for CapabilityError {}
+
+// `None` leaves that kind of capability unrestricted.
+#[derive(Clone, Debug, Default)]
+pub struct Capabilities {
+    functions: Option<HashSet<String>>,
+    operations: Option<HashSet<String>>,
+    components: Option<HashSet<String>>,
+    events: Option<HashSet<String>>,
+    scripts: Option<HashSet<String>>,
+    targets: Option<HashSet<String>>,
+}
+
+impl Capabilities {
+    pub fn all() -> Self {
+        Self::default()
+    }
+
+    pub fn none() -> Self {
+        Self {
+            functions: Some(HashSet::new()),
+            operations: Some(HashSet::new()),
+            components: Some(HashSet::new()),
+            events: Some(HashSet::new()),
+            scripts: Some(HashSet::new()),
+            targets: Some(HashSet::new()),
+        }
+    }
+
+    pub fn with_functions(mut self, ids: impl IntoIterator<Item = impl Into<String>>) -> Self {
+        allow(&mut self.functions, ids);
+        self
+    }
+
+    pub fn with_operations(mut self, ids: impl IntoIterator<Item = impl Into<String>>) -> Self {
+        allow(&mut self.operations, ids);
+        self
+    }
+
+    pub fn with_components(mut self, ids: impl IntoIterator<Item = impl Into<String>>) -> Self {
+        allow(&mut self.components, ids);
+        self
+    }
+
+    // Events that can be sent with the `emit` operation.
+    pub fn with_events(mut self, ids: impl IntoIterator<Item = impl Into<String>>) -> Self {
+        allow(&mut self.events, ids);
+        self
+    }
+
+    // Scripts that can be spawned by the `spawn` operation or declared as `children`.
+    pub fn with_scripts(mut self, ids: impl IntoIterator<Item = impl Into<String>>) -> Self {
+        allow(&mut self.scripts, ids);
+        self
+    }
+
+    // Targets other than `self`, like `parent` or `radius`.
+    pub fn with_targets(mut self, ids: impl IntoIterator<Item = impl Into<String>>) -> Self {
+        allow(&mut self.targets, ids);
+        self
+    }
+
+    pub fn check_function(&self, id: &str) -> Result<(), CapabilityError> {
+        check(&self.functions, id).map_err(CapabilityError::Function)
+    }
+
+    pub fn check_operation(&self, id: &str) -> Result<(), CapabilityError> {
+        check(&self.operations, id).map_err(CapabilityError::Operation)
+    }
+
+    pub fn check_component(&self, id: &str) -> Result<(), CapabilityError> {
+        check(&self.components, id).map_err(CapabilityError::Component)
+    }
+
+    pub fn check_event(&self, id: &str) -> Result<(), CapabilityError> {
+        check(&self.events, id).map_err(CapabilityError::Event)
+    }
+
+    pub fn check_script(&self, id: &str) -> Result<(), CapabilityError> {
+        check(&self.scripts, id).map_err(CapabilityError::Script)
+    }
+
+    pub fn check_target(&self, id: &str) -> Result<(), CapabilityError> {
+        check(&self.targets, id).map_err(CapabilityError::Target)
+    }
+
+    pub fn check_expr(&self, value: &Value) -> Result<(), CapabilityError> {
+        if let Value::Array(items) = value {
+            if let Some(Value::String(ident)) = items.first() {
+                // Parameters are filled in by the game, not the script.
+                if ident == "param" {
+                    return Ok(());
+                }
+                self.check_function(ident)?;
+            }
+
+            for item in items.iter().skip(1) {
+                self.check_expr(item)?;
+            }
+        }
+
+        Ok(())
+    }
+}
+
+// Templates are only complete once their params are filled in, so values that still hold
+// params are left to be parsed at spawn.
+pub(crate) fn parse<T: DeserializeOwned>(value: &Value) -> Result<Option<T>, CapabilityError> {
+    match serde_json::from_value(value.clone()) {
+        Ok(data) => Ok(Some(data)),
+        Err(_) if has_params(value) => Ok(None),
+        Err(error) => Err(CapabilityError::Invalid(error.to_string())),
+    }
+}
+
+fn has_params(value: &Value) -> bool {
+    match value {
+        Value::Array(items) => is_param(value) || items.iter().any(has_params),
+        Value::Object(map) => map.values().any(has_params),
+        _ => false,
+    }
+}
+
+fn is_param(value: &Value) -> bool {
+    matches!(value, Value::Array(items) if items.first().and_then(Value::as_str) == Some("param"))
+}
+
+fn allow(set: &mut Option<HashSet<String>>, ids: impl IntoIterator<Item = impl Into<String>>) {
+    set.get_or_insert_with(HashSet::new)
+        .extend(ids.into_iter().map(Into::into));
+}
+
+fn check(set: &Option<HashSet<String>>, id: &str) -> Result<(), String> {
+    match set {
+        Some(set) if !set.contains(id) => Err(id.to_owned()),
+        _ => Ok(()),
+    }
+}
+
+impl Registry {
+    // Checks the functions an expression uses, and that they get the arguments they take.
+    pub fn check_expr(
+        &self,
+        value: &Value,
+        capabilities: &Capabilities,
+    ) -> Result<(), CapabilityError> {
+        capabilities.check_expr(value)?;
+        self.check_args(value)
+    }
+
+    fn check_args(&self, value: &Value) -> Result<(), CapabilityError> {
+        let Value::Array(items) = value else {
+            return Ok(());
+        };
+
+        let Some(first) = items.first() else {
+            return Err(CapabilityError::Invalid(String::from(
+                "expected a function, found `[]`",
+            )));
+        };
+
+        if let Value::String(ident) = first {
+            if ident == "param" {
+                return Ok(());
+            }
+            let builder = self
+                .fns
+                .get(ident)
+                .ok_or_else(|| CapabilityError::Unknown(ident.clone()))?;
+
+            let args = &items[1..];
+            if let Some(arity) = builder.arity().filter(|arity| *arity != args.len()) {
+                return Err(CapabilityError::Invalid(format!(
+                    "`{ident}`: expected {arity} arguments, found {}",
+                    args.len()
+                )));
+            }
+
+            let invalid = |expected: &str, found: &Value| {
+                CapabilityError::Invalid(format!("`{ident}`: expected {expected}, found `{found}`"))
+            };
+            match builder.args() {
+                Args::Any => {}
+                Args::Numbers => {
+                    if args.is_empty() {
+                        return Err(CapabilityError::Invalid(format!(
+                            "`{ident}`: expected at least 1 argument, found 0"
+                        )));
+                    }
+                    if let Some(arg) = args.iter().find(|arg| !arg.is_number() && !arg.is_array()) {
+                        return Err(invalid("a number", arg));
+                    }
+                }
+                Args::Dependencies => {
+                    for arg in args.iter().filter(|arg| !is_param(arg)) {
+                        let name = arg.as_str().ok_or_else(|| invalid("a value id", arg))?;
+                        // Only registered values can be depended on.
+                        if !self.deps.contains_key(name) {
+                            return Err(CapabilityError::Unknown(name.to_owned()));
+                        }
+                    }
+                    return Ok(());
+                }
+            }
+        }
+
+        items
+            .iter()
+            .skip(1)
+            .try_for_each(|item| self.check_args(item))
+    }
+
+    // Only checks what `data` adds itself. Scripts it `extends` are checked against their own
+    // capabilities when they load, so a restricted pack can inherit from trusted base scripts.
+    pub fn check(
+        &self,
+        data: &ComponentsData,
+        capabilities: &Capabilities,
+    ) -> Result<(), CapabilityError> {
+        for (id, value) in contributed(data) {
+            if id == "children" {
+                self.check_children(&value, capabilities)
+                    .map_err(|error| error.within(&id))?;
+                continue;
+            }
+
+            let f = self
+                .check_fns
+                .get(&id)
+                .ok_or_else(|| CapabilityError::Unknown(id.clone()))?;
+            f(&value, self, capabilities).map_err(|error| error.within(&id))?;
+        }
+
+        Ok(())
+    }
+}
+
+// Collects everything a pack layer adds, whether it replaces, merges or patches a script.
+fn contributed(data: &ComponentsData) -> Map<String, Value> {
+    let mut values = layer(data);
+    values.retain(|key, _| {
+        !matches!(
+            key.as_str(),
+            "id" | "version" | "extends" | "remove" | "params"
+        )
+    });
+    values
+}
+
+fn layer(data: &ComponentsData) -> Map<String, Value> {
+    if let Some(patch) = data.0.get("merge") {
+        let mut patch = patch.clone();
+        strip_nulls(&mut patch);
+        return match patch {
+            Value::Object(values) => values,
+            _ => Map::new(),
+        };
+    }
+
+    if let Some(Value::Array(operations)) = data.0.get("patch") {
+        let mut values = Value::Object(Map::new());
+        for operation in operations {
+            if matches!(operation["op"].as_str(), Some("remove" | "test") | None) {
+                continue;
+            }
+            let Some(path) = operation["path"]
+                .as_str()
+                .and_then(|path| path.strip_prefix('/'))
+            else {
+                continue;
+            };
+
+            let mut value = operation.get("value").cloned().unwrap_or_default();
+            for key in path.rsplit('/') {
+                let key = key.replace("~1", "/").replace("~0", "~");
+                value = Value::Object(Map::from_iter([(key, value)]));
+            }
+            merge(&mut values, value);
+        }
+
+        return match values {
+            Value::Object(values) => values,
+            _ => Map::new(),
+        };
+    }
+
+    data.0
+        .iter()
+        .map(|(key, value)| (key.clone(), value.clone()))
+        .collect()
+}
+
+// Nulls in a merge patch remove keys, which never needs a capability.
+fn strip_nulls(value: &mut Value) {
+    if let Value::Object(map) = value {
+        map.retain(|_, value| !value.is_null());
+        for value in map.values_mut() {
+            strip_nulls(value);
+        }
+    }
+}
@@ -0,0 +1,178 @@
+use crate::{
+    AssetRegistry, ComponentsData, DespawnScript, DetachScript, FiredEvents, Registry,
+    ResolveError, SavedState, ScopeData, ScriptBundle, ScriptInstance, TimedOperations,
+};
+use bevy::{
+    asset::{AssetServer, Assets},
+    ecs::{
+        entity::Entity,
+        system::{CommandQueue, Commands, EntityCommands},
+        world::{EntityWorldMut, Mut, World},
+    },
+};
+
+pub trait ScriptCommandsExt {
+    fn spawn_script(&mut self, bundle: impl Into<ScriptBundle>) -> EntityCommands<'_>;
+}
+
+impl ScriptCommandsExt for Commands<'_, '_> {
+    fn spawn_script(&mut self, bundle: impl Into<ScriptBundle>) -> EntityCommands<'_> {
+        self.spawn(bundle.into())
+    }
+}
+
+pub trait ScriptEntityCommandsExt {
+    fn apply_script(&mut self, bundle: impl Into<ScriptBundle>) -> &mut Self;
+
+    fn despawn_script(&mut self);
+}
+
+impl ScriptEntityCommandsExt for EntityCommands<'_> {
+    fn apply_script(&mut self, bundle: impl Into<ScriptBundle>) -> &mut Self {
+        self.insert(bundle.into())
+    }
+
+    fn despawn_script(&mut self) {
+        let entity = self.id();
+        self.commands().add(DespawnScript(entity));
+    }
+}
+
+pub trait ScriptWorldExt {
+    fn spawn_script_now(&mut self, bundle: impl Into<ScriptBundle>)
+        -> Result<Entity, ResolveError>;
+}
+
+impl ScriptWorldExt for World {
+    fn spawn_script_now(
+        &mut self,
+        bundle: impl Into<ScriptBundle>,
+    ) -> Result<Entity, ResolveError> {
+        let bundle = bundle.into();
+        let data = self.resource_scope(|world, mut asset_registry: Mut<AssetRegistry>| {
+            bundle.resolve(
+                &mut asset_registry,
+                world.resource::<Registry>(),
+                world.resource::<Assets<ComponentsData>>(),
+            )
+        })?;
+
+        let entity = self.spawn_empty().id();
+        // Applied outside the scope, so commands can use the registry themselves.
+        let mut queue = CommandQueue::default();
+        self.resource_scope(|world, registry: Mut<Registry>| {
+            let mut commands = Commands::new(&mut queue, world);
+            bundle.apply(
+                &mut commands,
+                &registry,
+                world.resource::<AssetServer>(),
+                entity,
+                data,
+                false,
+            );
+        });
+        queue.apply(self);
+
+        Ok(entity)
+    }
+}
+
+impl ScriptBundle {
+    pub(crate) fn resolve(
+        &self,
+        asset_registry: &mut AssetRegistry,
+        registry: &Registry,
+        assets: &Assets<ComponentsData>,
+    ) -> Result<ComponentsData, ResolveError> {
+        let data = asset_registry.resolve(&self.id, assets)?;
+        if data.0.contains_key("params") {
+            let filled = data.clone().with_params(&self.id, &self.params)?;
+            asset_registry.check_params(registry, &self.id, &filled, assets)?;
+        }
+
+        data.with_overrides(self.overrides.0.clone())
+            .with_params(&self.id, &self.params)
+    }
+
+    pub(crate) fn apply(
+        &self,
+        commands: &mut Commands,
+        registry: &Registry,
+        asset_server: &AssetServer,
+        entity: Entity,
+        mut data: ComponentsData,
+        is_scripted: bool,
+    ) {
+        let state = self.state.clone().unwrap_or_default();
+        data.0.extend(state.added);
+        for id in &state.removed {
+            data.0.remove(id);
+        }
+
+        let instance = ScriptInstance {
+            id: self.id.clone(),
+            params: self.params.0.clone(),
+            overrides: self.overrides.0.clone(),
+            components: data.0.keys().cloned().collect(),
+            data: data.clone(),
+        };
+
+        // Re-scripting starts from a clean entity.
+        if is_scripted {
+            commands.add(DetachScript(entity));
+        }
+
+        // The instance goes first so operations can record what they add.
+        commands
+            .entity(entity)
+            .remove::<ScriptBundle>()
+            .insert(instance);
+
+        // Restored entities keep the effects of events that already fired,
+        // so those events are not run again.
+        for id in &state.fired {
+            data.0.remove(id);
+        }
+        registry.spawn(asset_server, &mut commands.entity(entity), data.0);
+
+        if self.state.is_some() {
+            let SavedState {
+                dependencies,
+                fired,
+                timers,
+                ..
+            } = state;
+
+            commands
+                .entity(entity)
+                .add(move |mut entity: EntityWorldMut| {
+                    if let Some(mut scope_data) = entity.get_mut::<ScopeData>() {
+                        for (id, value) in dependencies {
+                            scope_data.set_dependency(&id, value);
+                        }
+                    }
+
+                    if let Some(mut events) = entity.get_mut::<FiredEvents>() {
+                        events.0.extend(fired);
+                    } else {
+                        entity.insert(FiredEvents(fired.into_iter().collect()));
+                    }
+
+                    // Timers pick up where they were, so effects that only last a while still end.
+                    TimedOperations::restore(&mut entity, timers);
+                });
+        }
+    }
+}
+
+impl From<&str> for ScriptBundle {
+    fn from(id: &str) -> Self {
+        Self::new(id)
+    }
+}
+
+impl From<String> for ScriptBundle {
+    fn from(id: String) -> Self {
+        Self::new(id)
+    }
+}
@@ -0,0 +1,145 @@
+use crate::{
+    capability::parse,
+    expr::{ExprData, StaticExpr},
+    operation::deferred,
+    Capabilities, CapabilityError, Operation, Registry,
+};
+use bevy::{asset::AssetServer, ecs::system::EntityCommands};
+use serde::Deserialize;
+use serde_json::Value;
+use std::collections::HashMap;
+
+type Operations = HashMap<String, Value>;
+
+#[derive(Deserialize)]
+struct IfOperationData(ExprData, Operations, #[serde(default)] Operations);
+
+pub struct IfOperation;
+
+impl Operation for IfOperation {
+    fn spawn(
+        &self,
+        _registry: &Registry,
+        _asset_server: &AssetServer,
+        entity_commands: &mut EntityCommands,
+        value: Value,
+    ) {
+        let IfOperationData(condition, then, otherwise) = serde_json::from_value(value).unwrap();
+
+        deferred(
+            entity_commands,
+            move |registry, asset_server, entity, entity_commands| {
+                // Conditions that can't be run, like ones reading a value the entity doesn't
+                // derive, count as false.
+                let is_true = match registry.eval(&entity, condition) {
+                    Some(StaticExpr::Number(n)) => n != 0.,
+                    Some(StaticExpr::String(s)) => !s.is_empty(),
+                    None => false,
+                };
+
+                let operations = if is_true { then } else { otherwise };
+                registry.run_operations(asset_server, entity_commands, operations);
+            },
+        );
+    }
+
+    fn check(
+        &self,
+        registry: &Registry,
+        value: &Value,
+        capabilities: &Capabilities,
+    ) -> Result<(), CapabilityError> {
+        if parse::<IfOperationData>(value)?.is_none() {
+            return Ok(());
+        }
+        let Value::Array(items) = value else {
+            return Ok(());
+        };
+
+        let mut items = items.iter();
+        if let Some(condition) = items.next() {
+            registry.check_expr(condition, capabilities)?;
+        }
+        items.try_for_each(|operations| registry.check_operations(operations, capabilities))
+    }
+}
+
+fn check_each(
+    registry: &Registry,
+    value: &Value,
+    capabilities: &Capabilities,
+) -> Result<(), CapabilityError> {
+    match parse::<Vec<Value>>(value)? {
+        Some(items) => items
+            .iter()
+            .try_for_each(|operations| registry.check_operations(operations, capabilities)),
+        None => Ok(()),
+    }
+}
+
+// Runs each set of operations in order, unlike the keys of a single set.
+pub struct SeqOperation;
+
+impl Operation for SeqOperation {
+    fn spawn(
+        &self,
+        registry: &Registry,
+        asset_server: &AssetServer,
+        entity_commands: &mut EntityCommands,
+        value: Value,
+    ) {
+        let steps: Vec<Operations> = serde_json::from_value(value).unwrap();
+
+        for operations in steps {
+            registry.run_operations(asset_server, entity_commands, operations);
+        }
+    }
+
+    fn revert(&self, registry: &Registry, entity_commands: &mut EntityCommands, value: Value) {
+        let Ok(steps) = serde_json::from_value::<Vec<Operations>>(value) else {
+            return;
+        };
+
+        for operations in steps.into_iter().rev() {
+            registry.revert_operations(entity_commands, operations);
+        }
+    }
+
+    fn check(
+        &self,
+        registry: &Registry,
+        value: &Value,
+        capabilities: &Capabilities,
+    ) -> Result<(), CapabilityError> {
+        check_each(registry, value, capabilities)
+    }
+}
+
+pub struct RandomOneOperation;
+
+impl Operation for RandomOneOperation {
+    fn spawn(
+        &self,
+        registry: &Registry,
+        asset_server: &AssetServer,
+        entity_commands: &mut EntityCommands,
+        value: Value,
+    ) {
+        let mut choices: Vec<Operations> = serde_json::from_value(value).unwrap();
+        if choices.is_empty() {
+            return;
+        }
+
+        let operations = choices.swap_remove(fastrand::usize(..choices.len()));
+        registry.run_operations(asset_server, entity_commands, operations);
+    }
+
+    fn check(
+        &self,
+        registry: &Registry,
+        value: &Value,
+        capabilities: &Capabilities,
+    ) -> Result<(), CapabilityError> {
+        check_each(registry, value, capabilities)
+    }
+}
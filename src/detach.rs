@@ -0,0 +1,105 @@
+use crate::{FiredEvents, Registry, ScopeData, ScriptChild, ScriptInstance, TimedOperations};
+use bevy::{
+    ecs::{
+        entity::Entity,
+        system::{Command, CommandQueue, Commands, EntityCommands},
+        world::{EntityWorldMut, Mut, World},
+    },
+    hierarchy::{Children, DespawnRecursiveExt},
+};
+
+// Removes everything a script added to an entity, leaving components from other sources.
+pub struct DetachScript(pub Entity);
+
+impl Command for DetachScript {
+    fn apply(self, world: &mut World) {
+        let Some(instance) = world.get::<ScriptInstance>(self.0) else {
+            return;
+        };
+        let components = instance.components.clone();
+
+        let dependencies: Vec<_> = world
+            .get::<ScopeData>(self.0)
+            .map(|scope_data| scope_data.dependencies.keys().cloned().collect())
+            .unwrap_or_default();
+
+        let timers = world.get::<TimedOperations>(self.0).cloned();
+
+        let children: Vec<_> = world
+            .get::<Children>(self.0)
+            .map(|children| {
+                children
+                    .iter()
+                    .copied()
+                    .filter(|child| world.get::<ScriptChild>(*child).is_some())
+                    .collect()
+            })
+            .unwrap_or_default();
+
+        let mut queue = CommandQueue::default();
+        world.resource_scope(|world, registry: Mut<Registry>| {
+            let mut commands = Commands::new(&mut queue, world);
+
+            let mut entity_commands = commands.entity(self.0);
+            if let Some(timers) = timers {
+                timers.revert(&registry, &mut entity_commands);
+            }
+            registry.remove(&mut entity_commands, &components);
+            for id in &dependencies {
+                if let Some(dep) = registry.deps.get(id) {
+                    dep.remove(&mut entity_commands);
+                }
+            }
+            entity_commands.remove::<(ScopeData, FiredEvents, TimedOperations, ScriptInstance)>();
+
+            for child in children {
+                commands.add(DespawnScript(child));
+            }
+        });
+        queue.apply(world);
+    }
+}
+
+// Despawns an entity and its descendants, first undoing the `for` effects they still have
+// running, which could outlive them on other entities.
+pub struct DespawnScript(pub Entity);
+
+impl Command for DespawnScript {
+    fn apply(self, world: &mut World) {
+        let mut entities = vec![self.0];
+        let mut idx = 0;
+        while let Some(entity) = entities.get(idx).copied() {
+            if let Some(children) = world.get::<Children>(entity) {
+                entities.extend(children.iter().copied());
+            }
+            idx += 1;
+        }
+
+        // Reverted first, so targets like `parent` can still be found.
+        let mut queue = CommandQueue::default();
+        world.resource_scope(|world, registry: Mut<Registry>| {
+            let mut commands = Commands::new(&mut queue, world);
+            for entity in entities {
+                if let Some(timers) = world.get::<TimedOperations>(entity) {
+                    timers.revert(&registry, &mut commands.entity(entity));
+                }
+            }
+        });
+        queue.apply(world);
+
+        if let Some(entity) = world.get_entity_mut(self.0) {
+            entity.despawn_recursive();
+        }
+    }
+}
+
+// Records a component added after spawning, such as by an operation, so it is detached too.
+pub(crate) fn track(entity_commands: &mut EntityCommands, id: String) {
+    entity_commands.add(move |mut entity: EntityWorldMut| {
+        if let Some(mut instance) = entity.get_mut::<ScriptInstance>() {
+            if !instance.components.contains(&id) {
+                instance.components.push(id);
+            }
+        }
+    });
+}
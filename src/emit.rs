@@ -0,0 +1,114 @@
+use crate::{
+    capability::parse, expr::ExprData, operation::deferred, Capabilities, CapabilityError,
+    Operation, Registry,
+};
+use bevy::{
+    asset::AssetServer,
+    ecs::{system::EntityCommands, world::EntityRef},
+    log::error,
+};
+use serde_json::Value;
+use std::collections::HashMap;
+
+impl Registry {
+    // Arrays starting with a registered function are expressions, anything else is a plain value.
+    pub(crate) fn is_expr(&self, value: &Value) -> bool {
+        match value {
+            Value::Array(items) => items
+                .first()
+                .and_then(Value::as_str)
+                .is_some_and(|ident| self.fns.contains_key(ident)),
+            _ => false,
+        }
+    }
+
+    fn eval_payload(&self, entity: &EntityRef, value: Value) -> Value {
+        match value {
+            value if self.is_expr(&value) => serde_json::from_value::<ExprData>(value)
+                .ok()
+                .and_then(|data| self.eval(entity, data))
+                .map(|result| serde_json::to_value(result).unwrap())
+                .unwrap_or_default(),
+            Value::Array(items) => Value::Array(
+                items
+                    .into_iter()
+                    .map(|item| self.eval_payload(entity, item))
+                    .collect(),
+            ),
+            Value::Object(fields) => Value::Object(
+                fields
+                    .into_iter()
+                    .map(|(key, value)| (key, self.eval_payload(entity, value)))
+                    .collect(),
+            ),
+            value => value,
+        }
+    }
+
+    fn check_payload(
+        &self,
+        value: &Value,
+        capabilities: &Capabilities,
+    ) -> Result<(), CapabilityError> {
+        match value {
+            value if self.is_expr(value) => self.check_expr(value, capabilities),
+            Value::Array(items) => items
+                .iter()
+                .try_for_each(|item| self.check_payload(item, capabilities)),
+            Value::Object(fields) => fields
+                .values()
+                .try_for_each(|value| self.check_payload(value, capabilities)),
+            _ => Ok(()),
+        }
+    }
+}
+
+// Sends events registered with `ScriptPlugin::with_sent_event`, like
+// `{"emit": {"play_effect": {"name": "sparks"}}}`.
+pub struct EmitOperation;
+
+impl Operation for EmitOperation {
+    fn spawn(
+        &self,
+        _registry: &Registry,
+        _asset_server: &AssetServer,
+        entity_commands: &mut EntityCommands,
+        value: Value,
+    ) {
+        let events: HashMap<String, Value> = serde_json::from_value(value).unwrap();
+
+        deferred(
+            entity_commands,
+            move |registry, _asset_server, entity, entity_commands| {
+                for (id, payload) in events {
+                    let Some(f) = registry.send_fns.get(&id) else {
+                        error!("Failed to emit unknown event `{id}`.");
+                        continue;
+                    };
+                    let payload = registry.eval_payload(&entity, payload);
+                    f(payload, &mut entity_commands.commands());
+                }
+            },
+        );
+    }
+
+    fn check(
+        &self,
+        registry: &Registry,
+        value: &Value,
+        capabilities: &Capabilities,
+    ) -> Result<(), CapabilityError> {
+        let Some(events) = parse::<HashMap<String, Value>>(value)? else {
+            return Ok(());
+        };
+
+        for (id, payload) in &events {
+            capabilities.check_event(id)?;
+            if !registry.send_fns.contains_key(id) {
+                return Err(CapabilityError::Unknown(id.clone()));
+            }
+            registry.check_payload(payload, capabilities)?;
+        }
+        Ok(())
+    }
+}
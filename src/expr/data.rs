@@ -5,10 +5,11 @@ use bevy::{
     prelude::Component,
     reflect::TypePath,
 };
-use serde::{Deserialize, Deserializer};
+use serde::{ser::SerializeSeq, Deserialize, Deserializer, Serialize, Serializer};
 use serde_json::Value;
+use std::any::TypeId;
 
-#[derive(Clone, Debug, PartialEq, Deserialize, Asset, TypePath)]
+#[derive(Clone, Debug, PartialEq, Deserialize, Serialize, Asset, TypePath)]
 #[serde(untagged)]
 pub enum ExprData {
     Static(StaticExpr),
@@ -16,10 +17,10 @@ pub enum ExprData {
 }
 
 impl ExprData {
-    pub fn build(self, registry: &Registry) -> ScopeData {
-        let expr = self.build_expr(registry);
-        let dependencies = expr.deps().into_iter().map(|id| (id, None)).collect();
-        ScopeData { expr, dependencies }
+    pub fn build<C: Component>(self, registry: &Registry) -> ScopeData {
+        let mut scope_data = ScopeData::new::<C>(self.clone().build_expr(registry));
+        scope_data.sources.insert(TypeId::of::<C>(), self);
+        scope_data
     }
 
     pub fn build_expr(self, registry: &Registry) -> Expr {
@@ -46,7 +47,8 @@ impl Register for ExprData {
         _asset_server: &AssetServer,
         entity_commands: &mut bevy::ecs::system::EntityCommands,
     ) {
-        self.build(registry).spawn::<C>(registry, entity_commands)
+        self.build::<C>(registry)
+            .spawn::<C>(registry, entity_commands)
     }
 }
 
@@ -56,6 +58,20 @@ pub struct FunctionExprData {
     pub args: Vec<ExprData>,
 }
 
+impl Serialize for FunctionExprData {
+    fn serialize<S>(&self, serializer: S) -> Result<S::Ok, S::Error>
+    where
+        S: Serializer,
+    {
+        let mut seq = serializer.serialize_seq(Some(self.args.len() + 1))?;
+        seq.serialize_element(&self.ident)?;
+        for arg in &self.args {
+            seq.serialize_element(arg)?;
+        }
+        seq.end()
+    }
+}
+
 impl<'de> Deserialize<'de> for FunctionExprData {
     fn deserialize<D>(deserializer: D) -> Result<Self, D::Error>
     where
@@ -64,12 +80,15 @@ impl<'de> Deserialize<'de> for FunctionExprData {
         let value: Value = Deserialize::deserialize(deserializer)?;
 
         if let Value::Array(items) = value {
-            let ident = items[0].as_str().ok_or_else(|| {
+            let (ident, args) = items
+                .split_first()
+                .ok_or_else(|| serde::de::Error::custom("Expected a function identifier."))?;
+            let ident = ident.as_str().ok_or_else(|| {
                 serde::de::Error::custom("Expected string for function identifier.")
             })?;
 
             let ident = ident.to_owned();
-            let args = items[1..]
+            let args = args
                 .iter()
                 .map(|v| serde_json::from_value(v.clone()))
                 .collect::<Result<Vec<_>, _>>()
@@ -81,3 +100,41 @@ impl<'de> Deserialize<'de> for FunctionExprData {
         }
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use serde_json::json;
+
+    fn round_trip(value: Value) -> Value {
+        let data: ExprData = serde_json::from_value(value).unwrap();
+        serde_json::to_value(data).unwrap()
+    }
+
+    #[test]
+    fn lisp_round_trip() {
+        let text = r#"["+",1,["*",["@","damage"],2.5],["-",-3]]"#;
+        let data: ExprData = serde_json::from_str(text).unwrap();
+        assert_eq!(serde_json::to_string(&data).unwrap(), text);
+
+        for value in [
+            json!(["==", "fire", ["@", "element"]]),
+            json!(["/", 1e20, 0.1]),
+            json!(3),
+            json!(-0.5),
+            json!("fire"),
+        ] {
+            assert_eq!(round_trip(value.clone()), value);
+        }
+    }
+
+    #[test]
+    fn numbers_round_trip_by_value() {
+        assert_eq!(round_trip(json!(1.0)), json!(1));
+        assert_eq!(round_trip(json!(["+", 2.0, -4.0])), json!(["+", 2, -4]));
+
+        let zero = round_trip(json!(-0.0));
+        assert_eq!(zero, json!(-0.0));
+        assert!(zero.as_f64().unwrap().is_sign_negative());
+    }
+}
@@ -1,11 +1,11 @@
-use super::{Function, FunctionBuilder};
+use super::{Function, FunctionBuilder, NumberBuilder};
 use crate::{
     expr::{Expr, StaticExpr},
     ScopeData,
 };
 
 pub fn add() -> impl FunctionBuilder {
-    |args| AddFunction { args }
+    NumberBuilder(|args| AddFunction { args })
 }
 
 pub struct AddFunction {
@@ -0,0 +1,79 @@
+use super::{Function, FunctionBuilder};
+use crate::{
+    expr::{Expr, StaticExpr},
+    ScopeData,
+};
+
+pub fn lt() -> impl FunctionBuilder {
+    CompareBuilder(|a, b| numbers(a, b, |a, b| a < b))
+}
+
+pub fn le() -> impl FunctionBuilder {
+    CompareBuilder(|a, b| numbers(a, b, |a, b| a <= b))
+}
+
+pub fn gt() -> impl FunctionBuilder {
+    CompareBuilder(|a, b| numbers(a, b, |a, b| a > b))
+}
+
+pub fn ge() -> impl FunctionBuilder {
+    CompareBuilder(|a, b| numbers(a, b, |a, b| a >= b))
+}
+
+pub fn eq() -> impl FunctionBuilder {
+    CompareBuilder(|a, b| a == b)
+}
+
+pub fn ne() -> impl FunctionBuilder {
+    CompareBuilder(|a, b| a != b)
+}
+
+// Only numbers are ordered, so ordering anything else is false.
+fn numbers(a: &StaticExpr, b: &StaticExpr, compare: fn(f64, f64) -> bool) -> bool {
+    match (a, b) {
+        (StaticExpr::Number(a), StaticExpr::Number(b)) => compare(*a, *b),
+        _ => false,
+    }
+}
+
+pub struct CompareBuilder(fn(&StaticExpr, &StaticExpr) -> bool);
+
+impl FunctionBuilder for CompareBuilder {
+    type Function = CompareFunction;
+
+    fn build(&self, args: Vec<Expr>) -> Self::Function {
+        CompareFunction {
+            args,
+            compare: self.0,
+        }
+    }
+
+    fn arity(&self) -> Option<usize> {
+        Some(2)
+    }
+}
+
+// Comparisons return `1` when true and `0` when false, so they can be used as conditions.
+pub struct CompareFunction {
+    args: Vec<Expr>,
+    compare: fn(&StaticExpr, &StaticExpr) -> bool,
+}
+
+impl Function for CompareFunction {
+    fn dependencies(&self) -> Vec<String> {
+        self.args.iter().flat_map(|arg| arg.deps()).collect()
+    }
+
+    fn run(&self, scope: &ScopeData) -> f64 {
+        // Scripts are checked for two arguments when they load.
+        let [a, b] = self.args.as_slice() else {
+            return 0.;
+        };
+
+        if (self.compare)(&a.run(scope), &b.run(scope)) {
+            1.
+        } else {
+            0.
+        }
+    }
+}
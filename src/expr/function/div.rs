@@ -1,11 +1,11 @@
-use super::{Function, FunctionBuilder};
+use super::{Function, FunctionBuilder, NumberBuilder};
 use crate::{
     expr::{Expr, StaticExpr},
     ScopeData,
 };
 
 pub fn div() -> impl FunctionBuilder {
-    |args| DivFunction { args }
+    NumberBuilder(|args| DivFunction { args })
 }
 
 pub struct DivFunction {
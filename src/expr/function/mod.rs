@@ -5,6 +5,9 @@ use std::sync::Arc;
 mod add;
 pub use self::add::{add, AddFunction};
 
+mod compare;
+pub use self::compare::{eq, ge, gt, le, lt, ne, CompareBuilder, CompareFunction};
+
 mod div;
 pub use self::div::{div, DivFunction};
 
@@ -12,7 +15,7 @@ mod mul;
 pub use self::mul::{mul, MulFunction};
 
 mod query;
-pub use self::query::{query, QueryFunction};
+pub use self::query::{query, QueryBuilder, QueryFunction};
 
 mod sub;
 pub use self::sub::{sub, SubFunction};
@@ -21,6 +24,44 @@ pub trait FunctionBuilder: Send + Sync + 'static {
     type Function: Function;
 
     fn build(&self, args: Vec<Expr>) -> Self::Function;
+
+    // The number of arguments the function takes, checked when scripts load.
+    fn arity(&self) -> Option<usize> {
+        None
+    }
+
+    // The kind of arguments the function takes, checked when scripts load.
+    fn args(&self) -> Args {
+        Args::Any
+    }
+}
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum Args {
+    Any,
+    // At least one number, or an expression that returns one.
+    Numbers,
+    // Ids of values the entity depends on, like `damage`.
+    Dependencies,
+}
+
+// Builds functions that only take numbers, like `+`.
+pub struct NumberBuilder<F>(pub F);
+
+impl<F, Func> FunctionBuilder for NumberBuilder<F>
+where
+    F: Fn(Vec<Expr>) -> Func + Send + Sync + 'static,
+    Func: Function,
+{
+    type Function = Func;
+
+    fn build(&self, args: Vec<Expr>) -> Self::Function {
+        (self.0)(args)
+    }
+
+    fn args(&self) -> Args {
+        Args::Numbers
+    }
 }
 
 impl<F, Func> FunctionBuilder for F
@@ -43,10 +84,22 @@ pub trait Function: Send + Sync + 'static {
 
 pub(crate) trait DynFunctionBuilder: Send + Sync + 'static {
     fn dyn_build(&self, args: Vec<Expr>) -> Arc<dyn Function>;
+
+    fn arity(&self) -> Option<usize>;
+
+    fn args(&self) -> Args;
 }
 
 impl<F: FunctionBuilder> DynFunctionBuilder for F {
     fn dyn_build(&self, args: Vec<Expr>) -> Arc<dyn Function> {
         Arc::new(self.build(args))
     }
+
+    fn arity(&self) -> Option<usize> {
+        FunctionBuilder::arity(self)
+    }
+
+    fn args(&self) -> Args {
+        FunctionBuilder::args(self)
+    }
 }
@@ -1,11 +1,11 @@
-use super::{Function, FunctionBuilder};
+use super::{Function, FunctionBuilder, NumberBuilder};
 use crate::{
     expr::{Expr, StaticExpr},
     ScopeData,
 };
 
 pub fn mul() -> impl FunctionBuilder {
-    |args| MulFunction { args }
+    NumberBuilder(|args| MulFunction { args })
 }
 
 pub struct MulFunction {
@@ -1,11 +1,19 @@
-use super::{Function, FunctionBuilder};
+use super::{Args, Function, FunctionBuilder};
 use crate::{
     expr::{Expr, StaticExpr},
     ScopeData,
 };
 
 pub fn query() -> impl FunctionBuilder {
-    |args: Vec<Expr>| {
+    QueryBuilder
+}
+
+pub struct QueryBuilder;
+
+impl FunctionBuilder for QueryBuilder {
+    type Function = QueryFunction;
+
+    fn build(&self, args: Vec<Expr>) -> Self::Function {
         if let Some(Expr::Static(StaticExpr::String(s))) = args.first() {
             QueryFunction {
                 dependency: s.clone(),
@@ -14,6 +22,14 @@ pub fn query() -> impl FunctionBuilder {
             todo!()
         }
     }
+
+    fn arity(&self) -> Option<usize> {
+        Some(1)
+    }
+
+    fn args(&self) -> Args {
+        Args::Dependencies
+    }
 }
 
 pub struct QueryFunction {
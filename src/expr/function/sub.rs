@@ -1,11 +1,11 @@
-use super::{Function, FunctionBuilder};
+use super::{Function, FunctionBuilder, NumberBuilder};
 use crate::{
     expr::{Expr, StaticExpr},
     ScopeData,
 };
 
 pub fn sub() -> impl FunctionBuilder {
-    |args| SubFunction { args }
+    NumberBuilder(|args| SubFunction { args })
 }
 
 pub struct SubFunction {
@@ -1,6 +1,6 @@
 use crate::ScopeData;
 
-use serde::Deserialize;
+use serde::{Deserialize, Serialize, Serializer};
 use std::sync::Arc;
 
 mod data;
@@ -9,13 +9,25 @@ pub use self::data::{ExprData, FunctionExprData};
 pub mod function;
 use self::function::Function;
 
-#[derive(Clone, Debug, PartialEq, Deserialize)]
+#[derive(Clone, Debug, PartialEq, Deserialize, Serialize)]
 #[serde(untagged)]
 pub enum StaticExpr {
+    #[serde(serialize_with = "serialize_number")]
     Number(f64),
     String(String),
 }
 
+// Whole numbers are written back the way scripts usually spell them, as `1` rather than `1.0`,
+// so numbers round-trip by value rather than by spelling. `-0.0` keeps its sign.
+fn serialize_number<S: Serializer>(value: &f64, serializer: S) -> Result<S::Ok, S::Error> {
+    let is_negative_zero = *value == 0. && value.is_sign_negative();
+    if value.fract() == 0. && value.abs() < 9007199254740992. && !is_negative_zero {
+        serializer.serialize_i64(*value as i64)
+    } else {
+        serializer.serialize_f64(*value)
+    }
+}
+
 pub enum Expr {
     Static(StaticExpr),
     Dynamic(Arc<dyn Function>),
@@ -0,0 +1,101 @@
+use crate::{
+    capability::parse, Capabilities, CapabilityError, ComponentsData, Registry, ScriptBundle,
+    ScriptInstance,
+};
+use bevy::{
+    asset::AssetServer,
+    ecs::{component::Component, entity::Entity, system::EntityCommands, world::World},
+    hierarchy::{BuildChildren, Children},
+    log::error,
+};
+use serde::Deserialize;
+use serde_json::Value;
+use std::collections::HashMap;
+
+#[derive(Component)]
+pub struct ScriptChild;
+
+#[derive(Deserialize)]
+#[serde(untagged)]
+pub(crate) enum ChildData {
+    Script {
+        script: String,
+        #[serde(flatten)]
+        overrides: HashMap<String, Value>,
+    },
+    Inline(HashMap<String, Value>),
+}
+
+impl Registry {
+    pub(crate) fn spawn_children(
+        &self,
+        asset_server: &AssetServer,
+        entity_commands: &mut EntityCommands,
+        value: Value,
+    ) {
+        let children: Vec<ChildData> = match serde_json::from_value(value) {
+            Ok(children) => children,
+            Err(error) => {
+                error!("Failed to spawn children: {error}");
+                return;
+            }
+        };
+
+        entity_commands.with_children(|parent| {
+            for child in children {
+                match child {
+                    ChildData::Script { script, overrides } => {
+                        parent.spawn((
+                            ScriptBundle::new(script).with_overrides(overrides),
+                            ScriptChild,
+                        ));
+                    }
+                    ChildData::Inline(values) => {
+                        self.spawn(asset_server, &mut parent.spawn(ScriptChild), values);
+                    }
+                }
+            }
+        });
+    }
+
+    pub(crate) fn check_children(
+        &self,
+        value: &Value,
+        capabilities: &Capabilities,
+    ) -> Result<(), CapabilityError> {
+        let Some(children) = parse::<Vec<ChildData>>(value)? else {
+            return Ok(());
+        };
+
+        for child in children {
+            match child {
+                // Overrides are spawned like the script's own values.
+                ChildData::Script { script, overrides } => {
+                    capabilities.check_script(&script)?;
+                    self.check(&ComponentsData(overrides), capabilities)?;
+                }
+                ChildData::Inline(values) => self.check(&ComponentsData(values), capabilities)?,
+            }
+        }
+        Ok(())
+    }
+
+    // Script children keep their id, with their current values as overrides.
+    pub(crate) fn snapshot_children(&self, world: &World, entity: Entity) -> Vec<Value> {
+        let Some(children) = world.get::<Children>(entity) else {
+            return Vec::new();
+        };
+
+        children
+            .iter()
+            .filter(|child| world.get::<ScriptChild>(**child).is_some())
+            .map(|child| {
+                let mut values = self.snapshot(world, *child).0;
+                if let Some(instance) = world.get::<ScriptInstance>(*child) {
+                    values.insert(String::from("script"), Value::from(instance.id.clone()));
+                }
+                Value::Object(values.into_iter().collect())
+            })
+            .collect()
+    }
+}
@@ -1,27 +1,100 @@
 use bevy::{
-    asset::{Asset, AssetServer, Handle},
+    asset::{Asset, AssetServer, Handle, LoadedFolder},
     ecs::{
         component::Component,
+        entity::Entity,
         event::Event,
+        reflect::ReflectComponent,
         schedule::States,
-        system::{EntityCommands, Resource},
+        system::{Commands, EntityCommands, Resource},
+        world::{EntityRef, World},
+    },
+    log::error,
+    prelude::{Deref, DerefMut},
+    reflect::{
+        std_traits::ReflectDefault, Reflect, ReflectDeserialize, ReflectSerialize, TypePath,
     },
-    reflect::TypePath,
 };
-use serde::Deserialize;
+use serde::{Deserialize, Serialize};
 use serde_json::Value;
-use std::{collections::HashMap, marker::PhantomData, path::PathBuf, sync::Arc};
+use std::{
+    any::TypeId,
+    collections::HashMap,
+    marker::PhantomData,
+    path::PathBuf,
+    sync::{
+        atomic::{AtomicU64, Ordering},
+        Arc,
+    },
+    time::Duration,
+};
+
+mod cache;
+pub use self::cache::{CacheError, LoadScriptCache, ScriptCache};
+
+mod capability;
+pub use self::capability::{Capabilities, CapabilityError};
+
+mod commands;
+pub use self::commands::{ScriptCommandsExt, ScriptEntityCommandsExt, ScriptWorldExt};
+
+mod control;
+pub use self::control::{IfOperation, RandomOneOperation, SeqOperation};
+
+mod detach;
+pub use self::detach::{DespawnScript, DetachScript};
+
+mod emit;
+pub use self::emit::EmitOperation;
 
 pub mod expr;
+
+mod hierarchy;
 use self::expr::function::{DynFunctionBuilder, FunctionBuilder};
+pub use self::hierarchy::ScriptChild;
+
+mod migrate;
+pub use self::migrate::MigrationError;
+use self::migrate::MigrationFn;
+
+mod modifier;
+pub use self::modifier::{Modifier, ModifierKind, Modifiers, ModifyOperation};
+
+mod operation;
+pub use self::operation::{
+    AddOperation, AddOperationData, DespawnOperation, RemoveOperation, ReplaceOperation,
+    SetOperation, SpawnOperation, ToggleOperation,
+};
+
+mod pack;
+pub use self::pack::{LoadScriptPack, ScriptPack};
 
 mod plugin;
 pub use self::plugin::ScriptPlugin;
 
+mod pretty;
+pub use self::pretty::to_pretty_string;
+
+mod resolve;
+pub use self::resolve::ResolveError;
+use self::resolve::ResolvedScript;
+
+mod resolver;
+pub use self::resolver::{ScriptResolver, UnresolvedScript};
+
+mod save;
+pub use self::save::{FiredEvents, SavedScript, SavedState};
+
 mod scope;
 use self::scope::Dependency;
 pub use scope::{Scope, ScopeData};
 
+mod target;
+pub use self::target::{Tags, TriggeredBy};
+
+mod timed;
+pub use self::timed::{AfterOperation, EveryOperation, ForOperation, TimedOperations};
+
 #[derive(Component)]
 pub struct Depends<T> {
     id: String,
@@ -30,6 +103,12 @@ pub struct Depends<T> {
 
 pub trait ScriptComponent: Component {
     type Data: for<'de> Deserialize<'de> + Register;
+
+    // Components that weren't spawned from a script value, like ones added by operations, are
+    // left out of snapshots unless this is overridden or their data can be read from `null`.
+    fn snapshot(&self) -> Option<Value> {
+        None
+    }
 }
 
 pub trait Register {
@@ -43,12 +122,34 @@ pub trait Register {
 
 type SpawnFn = Arc<dyn Fn(Value, &Registry, &AssetServer, &mut EntityCommands) + Send + Sync>;
 
+type RemoveFn = Arc<dyn Fn(&mut EntityCommands) + Send + Sync>;
+
+type SnapshotFn = Arc<dyn Fn(&World, Entity, Option<&Value>) -> Option<Value> + Send + Sync>;
+
+type HasFn = Arc<dyn Fn(&EntityRef) -> bool + Send + Sync>;
+
+type ValueFn = Arc<dyn Fn(&EntityRef) -> Option<f64> + Send + Sync>;
+
+type SendFn = Arc<dyn Fn(Value, &mut Commands) + Send + Sync>;
+
+type CheckFn =
+    Arc<dyn Fn(&Value, &Registry, &Capabilities) -> Result<(), CapabilityError> + Send + Sync>;
+
 #[derive(Clone, Default, Resource)]
 pub struct Registry {
     spawn_fns: HashMap<String, SpawnFn>,
+    remove_fns: HashMap<String, RemoveFn>,
+    check_fns: HashMap<String, CheckFn>,
+    snapshot_fns: HashMap<String, SnapshotFn>,
+    has_fns: HashMap<String, HasFn>,
+    value_fns: HashMap<String, ValueFn>,
+    send_fns: HashMap<String, SendFn>,
     fns: HashMap<String, Arc<dyn DynFunctionBuilder>>,
     deps: HashMap<String, Arc<dyn Dependency>>,
     operations: HashMap<String, Arc<dyn Operation>>,
+    migrations: HashMap<u32, (u32, MigrationFn)>,
+    stats: HashMap<TypeId, String>,
+    events: HashMap<TypeId, String>,
 }
 
 impl Registry {
@@ -66,44 +167,343 @@ impl Registry {
         entity_commands: &mut EntityCommands,
         values: HashMap<String, Value>,
     ) {
+        // Events run last, so their operations see the rest of the script.
+        let (events, mut values): (Vec<_>, Vec<_>) = values
+            .into_iter()
+            .partition(|(name, _)| self.events.values().any(|event| event == name));
+        values.extend(events);
+
         for (name, value) in values {
-            self.spawn_fns.get(&name).unwrap()(value, self, asset_server, entity_commands)
+            if name == "children" {
+                self.spawn_children(asset_server, entity_commands, value);
+                continue;
+            }
+
+            // Scripts are checked when they load, but overrides are only known at spawn.
+            let Some(f) = self.spawn_fns.get(&name) else {
+                error!("Skipped unknown component `{name}`.");
+                continue;
+            };
+            f(value, self, asset_server, entity_commands)
+        }
+    }
+
+    pub fn snapshot(&self, world: &World, entity: Entity) -> ComponentsData {
+        let spawned = world
+            .get::<ScriptInstance>(entity)
+            .map(|instance| &instance.data.0);
+
+        let mut values: HashMap<_, _> = self
+            .snapshot_fns
+            .iter()
+            .filter_map(|(id, f)| {
+                let value = spawned.and_then(|spawned| spawned.get(id));
+                f(world, entity, value).map(|value| (id.clone(), value))
+            })
+            .collect();
+
+        let children = self.snapshot_children(world, entity);
+        if !children.is_empty() {
+            values.insert(String::from("children"), Value::Array(children));
         }
+        ComponentsData(values)
+    }
+
+    pub fn remove(&self, entity_commands: &mut EntityCommands, ids: &[String]) {
+        for id in ids {
+            if let Some(f) = self.remove_fns.get(id) {
+                f(entity_commands)
+            }
+        }
+    }
+
+    pub fn has(&self, entity: &EntityRef, id: &str) -> bool {
+        self.has_fns.get(id).is_some_and(|f| f(entity))
+    }
+
+    pub fn run_operations(
+        &self,
+        asset_server: &AssetServer,
+        entity_commands: &mut EntityCommands,
+        mut operations: HashMap<String, Value>,
+    ) {
+        if let Some(target) = operations.remove("target") {
+            target::run_operations(entity_commands, target, operations, false);
+            return;
+        }
+
+        for (id, value) in operations {
+            self.operations
+                .get(&id)
+                .unwrap()
+                .spawn(self, asset_server, entity_commands, value);
+        }
+    }
+
+    pub fn revert_operations(
+        &self,
+        entity_commands: &mut EntityCommands,
+        mut operations: HashMap<String, Value>,
+    ) {
+        if let Some(target) = operations.remove("target") {
+            target::run_operations(entity_commands, target, operations, true);
+            return;
+        }
+
+        for (id, value) in operations {
+            if let Some(operation) = self.operations.get(&id) {
+                operation.revert(self, entity_commands, value);
+            }
+        }
+    }
+
+    pub fn check_operations(
+        &self,
+        value: &Value,
+        capabilities: &Capabilities,
+    ) -> Result<(), CapabilityError> {
+        let Some(operations) = capability::parse::<HashMap<String, Value>>(value)? else {
+            return Ok(());
+        };
+
+        if let Some(value) = operations.get("target") {
+            target::check(value, capabilities).map_err(|error| error.within("target"))?;
+        }
+
+        for (id, operation_value) in operations.iter().filter(|(id, _)| *id != "target") {
+            capabilities.check_operation(id)?;
+            let operation = self
+                .operations
+                .get(id)
+                .ok_or_else(|| CapabilityError::Unknown(id.clone()))?;
+            operation
+                .check(self, operation_value, capabilities)
+                .map_err(|error| error.within(id))?;
+        }
+        Ok(())
     }
 }
 
-#[derive(Component)]
-pub struct ScriptBundle(pub String);
+#[derive(Clone, Debug, Default, PartialEq, Serialize, Deserialize, Deref, DerefMut, Reflect)]
+#[reflect_value(Default, PartialEq, Serialize, Deserialize)]
+pub struct ScriptValues(pub HashMap<String, Value>);
+
+#[derive(Component, Default, Reflect)]
+#[reflect(Component, Default)]
+pub struct ScriptBundle {
+    pub id: String,
+    pub params: ScriptValues,
+    pub overrides: ScriptValues,
+    #[reflect(ignore)]
+    pub state: Option<SavedState>,
+}
 
 impl ScriptBundle {
     pub fn new(id: impl Into<String>) -> Self {
-        Self(id.into())
+        Self::with_params(id, HashMap::new())
+    }
+
+    pub fn with_params(
+        id: impl Into<String>,
+        params: impl IntoIterator<Item = (String, Value)>,
+    ) -> Self {
+        Self {
+            id: id.into(),
+            params: ScriptValues(params.into_iter().collect()),
+            overrides: ScriptValues::default(),
+            state: None,
+        }
     }
+
+    pub fn with_override(mut self, id: impl Into<String>, value: impl Into<Value>) -> Self {
+        self.overrides.insert(id.into(), value.into());
+        self
+    }
+
+    pub fn with_overrides(mut self, overrides: impl IntoIterator<Item = (String, Value)>) -> Self {
+        self.overrides.extend(overrides);
+        self
+    }
+}
+
+#[derive(Component)]
+pub struct ScriptInstance {
+    pub id: String,
+    pub params: HashMap<String, Value>,
+    pub overrides: HashMap<String, Value>,
+    pub components: Vec<String>,
+    pub data: ComponentsData,
 }
 
 #[derive(Default, Resource)]
 pub struct AssetRegistry {
-    pub pending_handles: HashMap<String, Handle<ComponentsData>>,
+    pub pending_handles: HashMap<String, PendingLoad<ComponentsData>>,
+    pub pending_folders: HashMap<String, PendingLoad<LoadedFolder>>,
+    pub pending_caches: HashMap<String, PendingLoad<ScriptCache>>,
     pub handles: HashMap<String, Handle<ComponentsData>>,
+    pub packs: Vec<ScriptPack>,
+    resolved: HashMap<String, ResolvedScript>,
+    requested: HashMap<String, Duration>,
+    ref_counts: HashMap<String, usize>,
 }
 
-#[derive(Clone, Deserialize, Asset, TypePath)]
+#[derive(Clone, Deserialize, Serialize, Asset, TypePath)]
 pub struct ComponentsData(pub HashMap<String, Value>);
 
+impl AssetRegistry {
+    pub fn is_idle(&self) -> bool {
+        self.pending_handles.is_empty()
+            && self.pending_folders.is_empty()
+            && self.pending_caches.is_empty()
+    }
+
+    pub fn ref_count(&self, id: &str) -> usize {
+        self.ref_counts.get(id).copied().unwrap_or_default()
+    }
+}
+
+pub struct PendingLoad<A: Asset> {
+    pub tickets: Vec<LoadTicket>,
+    pub handle: Handle<A>,
+    pub pack: Option<usize>,
+}
+
+impl<A: Asset> PendingLoad<A> {
+    pub(crate) fn push(
+        pending: &mut HashMap<String, Self>,
+        path: String,
+        ticket: LoadTicket,
+        handle: Handle<A>,
+        pack: Option<usize>,
+    ) {
+        pending
+            .entry(path)
+            .or_insert_with(|| Self {
+                tickets: Vec::new(),
+                handle,
+                pack,
+            })
+            .tickets
+            .push(ticket);
+    }
+}
+
 #[derive(Clone, Copy, Debug, PartialEq, Eq, Hash, States)]
 pub enum ScriptState {
     Loading,
     Ready,
+    Failed,
+}
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash)]
+pub struct LoadTicket(u64);
+
+impl LoadTicket {
+    pub(crate) fn next() -> Self {
+        static NEXT: AtomicU64 = AtomicU64::new(0);
+        Self(NEXT.fetch_add(1, Ordering::Relaxed))
+    }
 }
 
 #[derive(Event)]
 pub struct LoadScript {
     path: PathBuf,
+    ticket: LoadTicket,
 }
 
 impl LoadScript {
     pub fn new(path: impl Into<PathBuf>) -> Self {
-        Self { path: path.into() }
+        Self {
+            path: path.into(),
+            ticket: LoadTicket::next(),
+        }
+    }
+
+    pub fn ticket(&self) -> LoadTicket {
+        self.ticket
+    }
+}
+
+#[derive(Event)]
+pub struct LoadScriptFolder {
+    path: PathBuf,
+    ticket: LoadTicket,
+}
+
+impl LoadScriptFolder {
+    pub fn new(path: impl Into<PathBuf>) -> Self {
+        Self {
+            path: path.into(),
+            ticket: LoadTicket::next(),
+        }
+    }
+
+    pub fn ticket(&self) -> LoadTicket {
+        self.ticket
+    }
+}
+
+#[derive(Event)]
+pub struct ScriptLoaded {
+    pub ticket: LoadTicket,
+    pub id: String,
+    pub path: String,
+}
+
+#[derive(Event)]
+pub struct ScriptLoadFailed {
+    pub ticket: LoadTicket,
+    pub path: String,
+    pub error: String,
+}
+
+#[derive(Clone, Copy, Debug, Default, Resource)]
+pub struct ScriptLoadProgress {
+    pub loaded: usize,
+    pub failed: usize,
+    pub total: usize,
+}
+
+impl ScriptLoadProgress {
+    pub fn fraction(&self) -> f32 {
+        if self.total == 0 {
+            1.
+        } else {
+            (self.loaded + self.failed) as f32 / self.total as f32
+        }
+    }
+}
+
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub enum UnloadEntities {
+    #[default]
+    Keep,
+    Despawn,
+    Strip,
+}
+
+#[derive(Event)]
+pub struct UnloadScript {
+    pub id: String,
+    pub entities: UnloadEntities,
+}
+
+impl UnloadScript {
+    pub fn new(id: impl Into<String>) -> Self {
+        Self {
+            id: id.into(),
+            entities: UnloadEntities::Keep,
+        }
+    }
+
+    pub fn despawn_entities(mut self) -> Self {
+        self.entities = UnloadEntities::Despawn;
+        self
+    }
+
+    pub fn strip_entities(mut self) -> Self {
+        self.entities = UnloadEntities::Strip;
+        self
     }
 }
 
@@ -118,36 +518,20 @@ pub trait Operation: Send + Sync {
         entity_commands: &mut EntityCommands,
         value: Value,
     );
-}
 
-pub struct AddOperation;
+    // Undoes `spawn`, for operations that only last a while.
+    fn revert(&self, _registry: &Registry, _entity_commands: &mut EntityCommands, _value: Value) {}
 
-impl Operation for AddOperation {
-    fn spawn(
+    fn check(
         &self,
-        registry: &Registry,
-        asset_server: &AssetServer,
-        entity_commands: &mut EntityCommands,
-        value: Value,
-    ) {
-        let data: AddOperationData = serde_json::from_value(value).unwrap();
-        match data {
-            AddOperationData::Single(id) => {
-                let f = registry.spawn_fns.get(&id).unwrap();
-                f(Value::default(), registry, asset_server, entity_commands);
-            }
-            AddOperationData::Many(_) => todo!(),
-        }
+        _registry: &Registry,
+        _value: &Value,
+        _capabilities: &Capabilities,
+    ) -> Result<(), CapabilityError> {
+        Ok(())
     }
 }
 
-#[derive(Deserialize)]
-#[serde(untagged)]
-pub enum AddOperationData {
-    Single(String),
-    Many(Vec<String>),
-}
-
 #[derive(Component)]
 pub struct EventMarker<T> {
     pub _marker: PhantomData<T>,
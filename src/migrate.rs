@@ -0,0 +1,64 @@
+use crate::{ComponentsData, Registry};
+use serde_json::Value;
+use std::{fmt, sync::Arc};
+
+pub(crate) type MigrationFn = Arc<dyn Fn(&mut ComponentsData) + Send + Sync>;
+
+#[derive(Clone, Debug, PartialEq)]
+pub enum MigrationError {
+    InvalidVersion(Value),
+}
+
+impl fmt::Display for MigrationError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            MigrationError::InvalidVersion(value) => {
+                write!(f, "Expected an integer `version`, found `{value}`.")
+            }
+        }
+    }
+}
+
+impl std::error::Error for MigrationError {}
+
+impl Registry {
+    pub fn add_migration(
+        &mut self,
+        from: u32,
+        to: u32,
+        f: impl Fn(&mut ComponentsData) + Send + Sync + 'static,
+    ) {
+        assert!(from < to, "Migrations must move to a newer version.");
+        self.migrations.insert(from, (to, Arc::new(f)));
+    }
+
+    pub fn needs_migration(&self, data: &ComponentsData) -> Result<bool, MigrationError> {
+        Ok(self.migrations.contains_key(&version(data)?))
+    }
+
+    // Scripts without a `version` predate versioning and start at 0.
+    pub fn migrate(&self, data: &mut ComponentsData) -> Result<(), MigrationError> {
+        let mut version = version(data)?;
+        if !self.migrations.contains_key(&version) {
+            return Ok(());
+        }
+
+        while let Some((to, f)) = self.migrations.get(&version) {
+            f(data);
+            version = *to;
+        }
+        data.0.insert(String::from("version"), Value::from(version));
+
+        Ok(())
+    }
+}
+
+fn version(data: &ComponentsData) -> Result<u32, MigrationError> {
+    match data.0.get("version") {
+        Some(value) => value
+            .as_u64()
+            .and_then(|version| u32::try_from(version).ok())
+            .ok_or_else(|| MigrationError::InvalidVersion(value.clone())),
+        None => Ok(0),
+    }
+}
@@ -0,0 +1,201 @@
+use crate::{
+    capability::parse, detach, Capabilities, CapabilityError, Operation, Register, Registry,
+    ScopeData, ScriptComponent,
+};
+use bevy::{
+    asset::AssetServer,
+    ecs::{
+        change_detection::DetectChangesMut,
+        component::Component,
+        removal_detection::RemovedComponents,
+        system::{EntityCommands, Query, Res},
+        world::EntityWorldMut,
+    },
+    time::Time,
+};
+use serde::{Deserialize, Serialize};
+use serde_json::Value;
+
+#[derive(Clone, Copy, Debug, PartialEq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum ModifierKind {
+    Flat(f64),
+    Percent(f64),
+    Multiply(f64),
+}
+
+#[derive(Clone, Debug, PartialEq, Serialize, Deserialize)]
+pub struct Modifier {
+    pub stat: String,
+    pub source: String,
+    #[serde(flatten)]
+    pub kind: ModifierKind,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub duration: Option<f32>,
+}
+
+#[derive(Clone, Debug, Default, Component, Serialize, Deserialize)]
+#[serde(transparent)]
+pub struct Modifiers(pub Vec<Modifier>);
+
+impl Modifiers {
+    pub fn add(&mut self, modifier: Modifier) {
+        self.0.push(modifier);
+    }
+
+    pub fn remove_source(&mut self, source: &str) {
+        self.0.retain(|modifier| modifier.source != source);
+    }
+
+    // Flat modifiers are added first, then percentages are summed and applied once,
+    // and multipliers are applied last.
+    pub fn apply(&self, stat: &str, base: f64) -> f64 {
+        let mut flat = 0.;
+        let mut percent = 0.;
+        let mut multiply = 1.;
+
+        for modifier in self.0.iter().filter(|modifier| modifier.stat == stat) {
+            match modifier.kind {
+                ModifierKind::Flat(value) => flat += value,
+                ModifierKind::Percent(value) => percent += value,
+                ModifierKind::Multiply(value) => multiply *= value,
+            }
+        }
+
+        (base + flat) * (1. + percent) * multiply
+    }
+
+    fn extend(entity_commands: &mut EntityCommands, modifiers: Vec<Modifier>) {
+        entity_commands.add(move |mut entity: EntityWorldMut| {
+            if let Some(mut current) = entity.get_mut::<Modifiers>() {
+                current.0.extend(modifiers);
+            } else {
+                entity.insert(Modifiers(modifiers));
+            }
+        });
+    }
+}
+
+impl ScriptComponent for Modifiers {
+    type Data = Modifiers;
+
+    fn snapshot(&self) -> Option<Value> {
+        serde_json::to_value(self).ok()
+    }
+}
+
+impl Register for Modifiers {
+    fn register<C: Component>(
+        self,
+        _registry: &Registry,
+        _asset_server: &AssetServer,
+        entity_commands: &mut EntityCommands,
+    ) {
+        Modifiers::extend(entity_commands, self.0);
+    }
+}
+
+#[derive(Deserialize)]
+#[serde(untagged)]
+enum ModifyOperationData {
+    Single(Modifier),
+    Many(Vec<Modifier>),
+}
+
+impl From<ModifyOperationData> for Vec<Modifier> {
+    fn from(data: ModifyOperationData) -> Self {
+        match data {
+            ModifyOperationData::Single(modifier) => vec![modifier],
+            ModifyOperationData::Many(modifiers) => modifiers,
+        }
+    }
+}
+
+pub struct ModifyOperation;
+
+impl Operation for ModifyOperation {
+    fn spawn(
+        &self,
+        _registry: &Registry,
+        _asset_server: &AssetServer,
+        entity_commands: &mut EntityCommands,
+        value: Value,
+    ) {
+        let data: ModifyOperationData = serde_json::from_value(value).unwrap();
+        Modifiers::extend(entity_commands, data.into());
+        detach::track(entity_commands, String::from("modifiers"));
+    }
+
+    fn revert(&self, _registry: &Registry, entity_commands: &mut EntityCommands, value: Value) {
+        let Ok(data) = serde_json::from_value::<ModifyOperationData>(value) else {
+            return;
+        };
+        let modifiers = Vec::from(data);
+
+        entity_commands.add(move |mut entity: EntityWorldMut| {
+            if let Some(mut current) = entity.get_mut::<Modifiers>() {
+                // Durations tick down, so only the rest of the modifier has to match.
+                current.0.retain(|modifier| {
+                    !modifiers.iter().any(|added| {
+                        added.stat == modifier.stat
+                            && added.source == modifier.source
+                            && added.kind == modifier.kind
+                    })
+                });
+            }
+        });
+    }
+
+    fn check(
+        &self,
+        registry: &Registry,
+        value: &Value,
+        capabilities: &Capabilities,
+    ) -> Result<(), CapabilityError> {
+        let Some(data) = parse::<ModifyOperationData>(value)? else {
+            return Ok(());
+        };
+
+        Vec::from(data).iter().try_for_each(|modifier| {
+            capabilities.check_component(&modifier.stat)?;
+            if !registry.value_fns.contains_key(&modifier.stat) {
+                return Err(CapabilityError::Unknown(modifier.stat.clone()));
+            }
+            Ok(())
+        })
+    }
+}
+
+// Derived values only run again when their scope or modifiers change, and removing the
+// modifiers isn't a change to either.
+pub(crate) fn remove_modifiers(
+    mut removed: RemovedComponents<Modifiers>,
+    mut query: Query<&mut ScopeData>,
+) {
+    for entity in removed.read() {
+        if let Ok(mut scope_data) = query.get_mut(entity) {
+            scope_data.set_changed();
+        }
+    }
+}
+
+pub(crate) fn expire_modifiers(time: Res<Time>, mut query: Query<&mut Modifiers>) {
+    let delta = time.delta_seconds();
+
+    for mut modifiers in &mut query {
+        // Only expiring a modifier counts as a change, so ticking doesn't re-run expressions.
+        let mut is_expired = false;
+        for modifier in &mut modifiers.bypass_change_detection().0 {
+            if let Some(duration) = &mut modifier.duration {
+                *duration -= delta;
+                is_expired |= *duration <= 0.;
+            }
+        }
+
+        if is_expired {
+            modifiers
+                .0
+                .retain(|modifier| modifier.duration.map_or(true, |duration| duration > 0.));
+        }
+    }
+}
@@ -0,0 +1,597 @@
+use crate::{
+    capability::parse,
+    detach,
+    expr::{ExprData, StaticExpr},
+    Capabilities, CapabilityError, ComponentsData, DespawnScript, Operation, Registry, ScopeData,
+    ScriptBundle,
+};
+use bevy::{
+    asset::AssetServer,
+    ecs::{
+        component::Component,
+        entity::Entity,
+        query::With,
+        system::{Command, CommandQueue, Commands, EntityCommands, Query},
+        world::{EntityRef, Mut, World},
+    },
+    hierarchy::{BuildChildren, Children, Parent},
+    log::error,
+};
+use serde::{de, Deserialize, Deserializer};
+use serde_json::Value;
+use std::collections::HashMap;
+
+#[derive(Deserialize)]
+#[serde(untagged)]
+pub enum AddOperationData {
+    Single(String),
+    Many(Vec<String>),
+    Payload(HashMap<String, Value>),
+}
+
+impl From<AddOperationData> for HashMap<String, Value> {
+    fn from(data: AddOperationData) -> Self {
+        match data {
+            AddOperationData::Single(id) => HashMap::from([(id, Value::default())]),
+            AddOperationData::Many(ids) => {
+                ids.into_iter().map(|id| (id, Value::default())).collect()
+            }
+            AddOperationData::Payload(values) => values,
+        }
+    }
+}
+
+#[derive(Deserialize)]
+#[serde(untagged)]
+enum ComponentIds {
+    Single(String),
+    Many(Vec<String>),
+}
+
+impl From<ComponentIds> for Vec<String> {
+    fn from(ids: ComponentIds) -> Self {
+        match ids {
+            ComponentIds::Single(id) => vec![id],
+            ComponentIds::Many(ids) => ids,
+        }
+    }
+}
+
+fn add(
+    registry: &Registry,
+    asset_server: &AssetServer,
+    entity_commands: &mut EntityCommands,
+    values: HashMap<String, Value>,
+) {
+    for (id, value) in values {
+        let f = registry.spawn_fns.get(&id).unwrap();
+        f(value, registry, asset_server, entity_commands);
+        detach::track(entity_commands, id);
+    }
+}
+
+fn check_add(
+    registry: &Registry,
+    values: &HashMap<String, Value>,
+    capabilities: &Capabilities,
+) -> Result<(), CapabilityError> {
+    for (id, value) in values {
+        check_id(registry, id, capabilities)?;
+        // Ids without a value are added from `null`, which not every component can be read from.
+        if let Some(f) = registry.check_fns.get(id) {
+            f(value, registry, capabilities).map_err(|error| error.within(id))?;
+        }
+    }
+    Ok(())
+}
+
+fn check_id(
+    registry: &Registry,
+    id: &str,
+    capabilities: &Capabilities,
+) -> Result<(), CapabilityError> {
+    capabilities.check_component(id)?;
+    if !registry.spawn_fns.contains_key(id) {
+        return Err(CapabilityError::Unknown(id.to_owned()));
+    }
+    Ok(())
+}
+
+fn check_ids(
+    registry: &Registry,
+    value: &Value,
+    capabilities: &Capabilities,
+) -> Result<(), CapabilityError> {
+    match parse::<ComponentIds>(value)? {
+        Some(ids) => Vec::from(ids)
+            .iter()
+            .try_for_each(|id| check_id(registry, id, capabilities)),
+        None => Ok(()),
+    }
+}
+
+// Runs `f` when the command is applied, for operations that depend on the entity's current
+// components.
+pub(crate) fn deferred(
+    entity_commands: &mut EntityCommands,
+    f: impl FnOnce(&Registry, &AssetServer, EntityRef, &mut EntityCommands) + Send + 'static,
+) {
+    entity_commands.add(move |entity: Entity, world: &mut World| {
+        let mut queue = CommandQueue::default();
+        world.resource_scope(|world, registry: Mut<Registry>| {
+            let asset_server = world.resource::<AssetServer>().clone();
+            let mut commands = Commands::new(&mut queue, world);
+
+            let Some(entity_ref) = world.get_entity(entity) else {
+                return;
+            };
+            f(
+                &registry,
+                &asset_server,
+                entity_ref,
+                &mut commands.entity(entity),
+            );
+        });
+        queue.apply(world);
+    });
+}
+
+pub struct AddOperation;
+
+impl Operation for AddOperation {
+    fn spawn(
+        &self,
+        registry: &Registry,
+        asset_server: &AssetServer,
+        entity_commands: &mut EntityCommands,
+        value: Value,
+    ) {
+        let data: AddOperationData = serde_json::from_value(value).unwrap();
+        add(registry, asset_server, entity_commands, data.into());
+    }
+
+    fn revert(&self, registry: &Registry, entity_commands: &mut EntityCommands, value: Value) {
+        let Ok(data) = serde_json::from_value::<AddOperationData>(value) else {
+            return;
+        };
+        let ids: Vec<_> = HashMap::from(data).into_keys().collect();
+        registry.remove(entity_commands, &ids);
+    }
+
+    fn check(
+        &self,
+        registry: &Registry,
+        value: &Value,
+        capabilities: &Capabilities,
+    ) -> Result<(), CapabilityError> {
+        match parse::<AddOperationData>(value)? {
+            Some(data) => check_add(registry, &data.into(), capabilities),
+            None => Ok(()),
+        }
+    }
+}
+
+pub struct RemoveOperation;
+
+impl Operation for RemoveOperation {
+    fn spawn(
+        &self,
+        registry: &Registry,
+        _asset_server: &AssetServer,
+        entity_commands: &mut EntityCommands,
+        value: Value,
+    ) {
+        let ids: ComponentIds = serde_json::from_value(value).unwrap();
+        registry.remove(entity_commands, &Vec::from(ids));
+    }
+
+    fn check(
+        &self,
+        registry: &Registry,
+        value: &Value,
+        capabilities: &Capabilities,
+    ) -> Result<(), CapabilityError> {
+        check_ids(registry, value, capabilities)
+    }
+}
+
+impl Registry {
+    // Runs an expression once against the entity's current values, rather than keeping it
+    // up to date like a derived value. Only this entity's derived values can be read, so other
+    // components and other entities' values (use a `target`) leave it without a result.
+    pub(crate) fn eval(&self, entity: &EntityRef, data: ExprData) -> Option<StaticExpr> {
+        let expr = data.build_expr(self);
+        let dependencies = expr
+            .deps()
+            .into_iter()
+            .map(|id| {
+                let value = self.value_fns.get(&id)?(entity)?;
+                Some((id, Some(value)))
+            })
+            .collect::<Option<_>>()?;
+
+        let scope_data = ScopeData {
+            exprs: HashMap::new(),
+            sources: HashMap::new(),
+            dependencies,
+        };
+        Some(expr.run(&scope_data))
+    }
+}
+
+pub struct SetOperation;
+
+impl Operation for SetOperation {
+    fn spawn(
+        &self,
+        _registry: &Registry,
+        _asset_server: &AssetServer,
+        entity_commands: &mut EntityCommands,
+        value: Value,
+    ) {
+        let values: HashMap<String, Value> = serde_json::from_value(value).unwrap();
+
+        deferred(
+            entity_commands,
+            move |registry, asset_server, entity, entity_commands| {
+                for (id, value) in values {
+                    let value = match serde_json::from_value::<ExprData>(value.clone()) {
+                        Ok(data @ ExprData::Dynamic(_)) if registry.value_fns.contains_key(&id) => {
+                            let Some(result) = registry.eval(&entity, data) else {
+                                error!("Failed to set `{id}`: its expression has unknown values.");
+                                continue;
+                            };
+                            serde_json::to_value(result).unwrap()
+                        }
+                        _ => value,
+                    };
+
+                    add(
+                        registry,
+                        asset_server,
+                        entity_commands,
+                        HashMap::from([(id, value)]),
+                    );
+                }
+            },
+        );
+    }
+
+    fn check(
+        &self,
+        registry: &Registry,
+        value: &Value,
+        capabilities: &Capabilities,
+    ) -> Result<(), CapabilityError> {
+        let Some(values) = parse::<HashMap<String, Value>>(value)? else {
+            return Ok(());
+        };
+
+        // Only derived values can run an expression, anything else would get the expression itself.
+        for (id, value) in &values {
+            if registry.is_expr(value) && !registry.value_fns.contains_key(id) {
+                return Err(CapabilityError::Invalid(format!(
+                    "`{id}`: only derived values can be set to an expression"
+                )));
+            }
+        }
+        check_add(registry, &values, capabilities)
+    }
+}
+
+pub struct ToggleOperation;
+
+impl Operation for ToggleOperation {
+    fn spawn(
+        &self,
+        _registry: &Registry,
+        _asset_server: &AssetServer,
+        entity_commands: &mut EntityCommands,
+        value: Value,
+    ) {
+        let ids: Vec<String> = serde_json::from_value::<ComponentIds>(value)
+            .unwrap()
+            .into();
+
+        deferred(
+            entity_commands,
+            move |registry, asset_server, entity, entity_commands| {
+                for id in ids {
+                    if registry.has(&entity, &id) {
+                        registry.remove(entity_commands, &[id]);
+                    } else {
+                        add(
+                            registry,
+                            asset_server,
+                            entity_commands,
+                            HashMap::from([(id, Value::default())]),
+                        );
+                    }
+                }
+            },
+        );
+    }
+
+    fn check(
+        &self,
+        registry: &Registry,
+        value: &Value,
+        capabilities: &Capabilities,
+    ) -> Result<(), CapabilityError> {
+        match parse::<ComponentIds>(value)? {
+            Some(ids) => {
+                let values = Vec::from(ids)
+                    .into_iter()
+                    .map(|id| (id, Value::default()))
+                    .collect();
+                check_add(registry, &values, capabilities)
+            }
+            None => Ok(()),
+        }
+    }
+}
+
+// Swaps components that are present for others, like `{"burning": "wet"}`.
+pub struct ReplaceOperation;
+
+impl Operation for ReplaceOperation {
+    fn spawn(
+        &self,
+        _registry: &Registry,
+        _asset_server: &AssetServer,
+        entity_commands: &mut EntityCommands,
+        value: Value,
+    ) {
+        let replacements: HashMap<String, AddOperationData> =
+            serde_json::from_value(value).unwrap();
+
+        deferred(
+            entity_commands,
+            move |registry, asset_server, entity, entity_commands| {
+                for (id, data) in replacements {
+                    if registry.has(&entity, &id) {
+                        registry.remove(entity_commands, &[id]);
+                        add(registry, asset_server, entity_commands, data.into());
+                    }
+                }
+            },
+        );
+    }
+
+    fn check(
+        &self,
+        registry: &Registry,
+        value: &Value,
+        capabilities: &Capabilities,
+    ) -> Result<(), CapabilityError> {
+        let Some(replacements) = parse::<HashMap<String, AddOperationData>>(value)? else {
+            return Ok(());
+        };
+
+        for (id, data) in replacements {
+            check_id(registry, &id, capabilities)?;
+            check_add(registry, &data.into(), capabilities)?;
+        }
+        Ok(())
+    }
+}
+
+#[derive(Clone, Copy, Default, Deserialize)]
+#[serde(rename_all = "snake_case")]
+enum SpawnAs {
+    Child,
+    Sibling,
+    #[default]
+    Root,
+}
+
+#[derive(Deserialize)]
+struct SpawnData {
+    script: String,
+    #[serde(default, rename = "as")]
+    spawn_as: SpawnAs,
+    #[serde(default)]
+    overrides: HashMap<String, Value>,
+}
+
+struct SpawnOperationData(Vec<SpawnData>);
+
+// Not untagged, so a typo like `"as": "childs"` reports the bad field instead of no match.
+impl<'de> Deserialize<'de> for SpawnOperationData {
+    fn deserialize<D>(deserializer: D) -> Result<Self, D::Error>
+    where
+        D: Deserializer<'de>,
+    {
+        let value = Value::deserialize(deserializer)?;
+        let spawns = match value {
+            Value::Array(_) => serde_json::from_value(value),
+            _ => serde_json::from_value(value).map(|spawn| vec![spawn]),
+        };
+        spawns.map(SpawnOperationData).map_err(de::Error::custom)
+    }
+}
+
+pub struct SpawnOperation;
+
+impl Operation for SpawnOperation {
+    fn spawn(
+        &self,
+        _registry: &Registry,
+        _asset_server: &AssetServer,
+        entity_commands: &mut EntityCommands,
+        value: Value,
+    ) {
+        let SpawnOperationData(spawns) = match serde_json::from_value(value) {
+            Ok(data) => data,
+            Err(error) => {
+                error!("Failed to spawn script: {error}");
+                return;
+            }
+        };
+
+        for spawn in spawns {
+            let bundle = ScriptBundle::new(spawn.script).with_overrides(spawn.overrides);
+
+            match spawn.spawn_as {
+                SpawnAs::Child => {
+                    entity_commands.with_children(|parent| {
+                        parent.spawn(bundle);
+                    });
+                }
+                SpawnAs::Sibling => deferred(entity_commands, |_, _, entity, entity_commands| {
+                    let mut commands = entity_commands.commands();
+                    match entity.get::<Parent>() {
+                        Some(parent) => {
+                            commands.entity(parent.get()).with_children(|parent| {
+                                parent.spawn(bundle);
+                            });
+                        }
+                        None => {
+                            commands.spawn(bundle);
+                        }
+                    }
+                }),
+                SpawnAs::Root => {
+                    entity_commands.commands().spawn(bundle);
+                }
+            }
+        }
+    }
+
+    fn check(
+        &self,
+        registry: &Registry,
+        value: &Value,
+        capabilities: &Capabilities,
+    ) -> Result<(), CapabilityError> {
+        let Some(SpawnOperationData(spawns)) = parse(value)? else {
+            return Ok(());
+        };
+
+        for spawn in spawns {
+            capabilities.check_script(&spawn.script)?;
+            // Overrides are spawned like the script's own values.
+            registry.check(&ComponentsData(spawn.overrides), capabilities)?;
+        }
+        Ok(())
+    }
+}
+
+#[derive(Deserialize)]
+#[serde(rename_all = "snake_case")]
+enum DespawnTarget {
+    #[serde(rename = "self")]
+    Entity,
+    Children,
+}
+
+// Despawning right away would break commands queued after it for the same entity,
+// such as the rest of the event's operations.
+#[derive(Component)]
+pub(crate) struct PendingDespawn;
+
+pub struct DespawnOperation;
+
+impl Operation for DespawnOperation {
+    fn spawn(
+        &self,
+        _registry: &Registry,
+        _asset_server: &AssetServer,
+        entity_commands: &mut EntityCommands,
+        value: Value,
+    ) {
+        match serde_json::from_value(value).unwrap() {
+            DespawnTarget::Entity => {
+                entity_commands.insert(PendingDespawn);
+            }
+            DespawnTarget::Children => {
+                entity_commands.add(|entity: Entity, world: &mut World| {
+                    let children: Vec<_> = world
+                        .get::<Children>(entity)
+                        .map(|children| children.to_vec())
+                        .unwrap_or_default();
+                    for child in children {
+                        DespawnScript(child).apply(world);
+                    }
+                });
+            }
+        }
+    }
+
+    fn check(
+        &self,
+        _registry: &Registry,
+        value: &Value,
+        _capabilities: &Capabilities,
+    ) -> Result<(), CapabilityError> {
+        parse::<DespawnTarget>(value).map(|_| ())
+    }
+}
+
+pub(crate) fn despawn_pending(mut commands: Commands, query: Query<Entity, With<PendingDespawn>>) {
+    for entity in &query {
+        commands.add(DespawnScript(entity));
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{expr::function, Tags};
+    use serde_json::json;
+    use std::sync::Arc;
+
+    #[derive(Component)]
+    struct Damage(f64);
+
+    fn registry() -> Registry {
+        let mut registry = Registry::default();
+        registry.add_function("@", function::query());
+        registry.add_function(">=", function::ge());
+        registry.add_function("==", function::eq());
+        registry.value_fns.insert(
+            String::from("damage"),
+            Arc::new(|entity| entity.get::<Damage>().map(|damage| damage.0)),
+        );
+        registry
+    }
+
+    fn eval(world: &World, entity: Entity, value: Value) -> Option<StaticExpr> {
+        let data = serde_json::from_value(value).unwrap();
+        registry().eval(&world.entity(entity), data)
+    }
+
+    #[test]
+    fn eval_reads_derived_values() {
+        let mut world = World::new();
+        let entity = world.spawn(Damage(3.)).id();
+
+        let condition = json!([">=", ["@", "damage"], 2]);
+        assert_eq!(
+            eval(&world, entity, condition),
+            Some(StaticExpr::Number(1.))
+        );
+
+        let condition = json!(["==", "fire", "fire"]);
+        assert_eq!(
+            eval(&world, entity, condition),
+            Some(StaticExpr::Number(1.))
+        );
+    }
+
+    #[test]
+    fn eval_without_derived_value() {
+        let mut world = World::new();
+        let entity = world.spawn(Tags::default()).id();
+
+        // Missing values and components that aren't derived can't be read.
+        assert_eq!(
+            eval(&world, entity, json!([">=", ["@", "damage"], 2])),
+            None
+        );
+        assert_eq!(
+            eval(&world, entity, json!(["==", ["@", "tags"], "fire"])),
+            None
+        );
+    }
+}
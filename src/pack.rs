@@ -0,0 +1,183 @@
+use crate::{AssetRegistry, Capabilities, ComponentsData, LoadTicket, Registry, ResolveError};
+use bevy::{
+    asset::{Assets, Handle},
+    ecs::event::Event,
+};
+use serde_json::Value;
+use std::{collections::HashMap, path::PathBuf};
+
+pub(crate) type Sources = HashMap<String, Option<String>>;
+
+pub struct ScriptPack {
+    pub name: String,
+    pub handles: HashMap<String, Handle<ComponentsData>>,
+    pub capabilities: Capabilities,
+}
+
+#[derive(Event)]
+pub struct LoadScriptPack {
+    pub(crate) name: String,
+    pub(crate) path: PathBuf,
+    pub(crate) ticket: LoadTicket,
+    pub(crate) capabilities: Capabilities,
+}
+
+impl LoadScriptPack {
+    pub fn new(name: impl Into<String>, path: impl Into<PathBuf>) -> Self {
+        Self {
+            name: name.into(),
+            path: path.into(),
+            ticket: LoadTicket::next(),
+            capabilities: Capabilities::all(),
+        }
+    }
+
+    pub fn with_capabilities(mut self, capabilities: Capabilities) -> Self {
+        self.capabilities = capabilities;
+        self
+    }
+
+    pub fn ticket(&self) -> LoadTicket {
+        self.ticket
+    }
+}
+
+impl AssetRegistry {
+    pub(crate) fn pack_index(&mut self, name: &str) -> usize {
+        if let Some(idx) = self.packs.iter().position(|pack| pack.name == name) {
+            idx
+        } else {
+            self.packs.push(ScriptPack {
+                name: name.to_owned(),
+                handles: HashMap::new(),
+                capabilities: Capabilities::all(),
+            });
+            self.packs.len() - 1
+        }
+    }
+
+    pub(crate) fn all_handles(
+        &self,
+    ) -> impl Iterator<Item = (&String, &Handle<ComponentsData>)> + '_ {
+        self.handles
+            .iter()
+            .chain(self.packs.iter().flat_map(|pack| pack.handles.iter()))
+    }
+
+    // Values holding params are only checked once they're filled in, each against the
+    // capabilities of the pack it came from.
+    pub(crate) fn check_params(
+        &mut self,
+        registry: &Registry,
+        id: &str,
+        data: &ComponentsData,
+        assets: &Assets<ComponentsData>,
+    ) -> Result<(), ResolveError> {
+        let sources = self.provenance(id, assets)?;
+        let all = Capabilities::all();
+
+        for (key, value) in &data.0 {
+            let capabilities = match sources.get(key) {
+                Some(Some(name)) => self
+                    .packs
+                    .iter()
+                    .find(|pack| &pack.name == name)
+                    .map_or(&all, |pack| &pack.capabilities),
+                _ => &all,
+            };
+
+            let values = ComponentsData(HashMap::from([(key.clone(), value.clone())]));
+            registry
+                .check(&values, capabilities)
+                .map_err(|error| ResolveError::Rejected(id.to_owned(), error))?;
+        }
+
+        Ok(())
+    }
+
+    // Applies every pack in load order over the base definition of `id`.
+    pub(crate) fn layered(
+        &self,
+        id: &str,
+        assets: &Assets<ComponentsData>,
+    ) -> Result<(HashMap<String, Value>, Sources), ResolveError> {
+        let mut values = None;
+        let mut sources = Sources::new();
+
+        if let Some(handle) = self.handles.get(id) {
+            let data = assets
+                .get(handle)
+                .ok_or_else(|| ResolveError::Loading(id.to_owned()))?;
+
+            sources = data.0.keys().map(|key| (key.clone(), None)).collect();
+            values = Some(Value::Object(data.0.clone().into_iter().collect()));
+        }
+
+        for pack in &self.packs {
+            let Some(handle) = pack.handles.get(id) else {
+                continue;
+            };
+            let data = assets
+                .get(handle)
+                .ok_or_else(|| ResolveError::Loading(id.to_owned()))?;
+
+            let mut layer = data.0.clone();
+            if let Some(patch) = layer.remove("merge") {
+                let target = values
+                    .as_mut()
+                    .ok_or_else(|| ResolveError::Missing(id.to_owned()))?;
+                json_patch::merge(target, &patch);
+
+                if let Value::Object(patch) = patch {
+                    for key in patch.keys() {
+                        sources.insert(key.clone(), Some(pack.name.clone()));
+                    }
+                }
+            } else if let Some(patch) = layer.remove("patch") {
+                let target = values
+                    .as_mut()
+                    .ok_or_else(|| ResolveError::Missing(id.to_owned()))?;
+                let operations: json_patch::Patch =
+                    serde_json::from_value(patch.clone()).map_err(|error| {
+                        ResolveError::InvalidPatch(id.to_owned(), error.to_string())
+                    })?;
+                json_patch::patch(target, &operations).map_err(|error| {
+                    ResolveError::InvalidPatch(id.to_owned(), error.to_string())
+                })?;
+
+                for key in patched_keys(&patch) {
+                    sources.insert(key, Some(pack.name.clone()));
+                }
+            } else {
+                sources = layer
+                    .keys()
+                    .map(|key| (key.clone(), Some(pack.name.clone())))
+                    .collect();
+                values = Some(Value::Object(layer.into_iter().collect()));
+            }
+        }
+
+        match values {
+            Some(Value::Object(values)) => {
+                let values: HashMap<_, _> = values.into_iter().collect();
+                sources.retain(|key, _| values.contains_key(key));
+                Ok((values, sources))
+            }
+            _ => Err(ResolveError::Missing(id.to_owned())),
+        }
+    }
+}
+
+fn patched_keys(patch: &Value) -> Vec<String> {
+    let Value::Array(operations) = patch else {
+        return Vec::new();
+    };
+
+    operations
+        .iter()
+        .filter(|operation| operation["op"] != "test")
+        .filter_map(|operation| operation["path"].as_str())
+        .filter_map(|path| path.split('/').nth(1))
+        .map(|key| key.replace("~1", "/").replace("~0", "~"))
+        .collect()
+}
@@ -1,29 +1,42 @@
 use crate::{
+    cache::ScriptCacheLoader,
+    capability::parse,
     expr::{
         function::{self, FunctionBuilder},
         StaticExpr,
     },
-    AddOperation, AssetRegistry, ComponentsData, Depends, EventMarker, LoadScript, Operation,
-    Register, Registry, Scope, ScopeData, ScriptBundle, ScriptComponent, ScriptState, ScriptsReady,
+    modifier, operation, timed, AddOperation, AfterOperation, AssetRegistry, CacheError,
+    Capabilities, ComponentsData, Depends, DespawnOperation, DespawnScript, DetachScript,
+    EmitOperation, EventMarker, EveryOperation, FiredEvents, ForOperation, IfOperation, LoadScript,
+    LoadScriptCache, LoadScriptFolder, LoadScriptPack, LoadTicket, Modifiers, ModifyOperation,
+    Operation, PendingLoad, RandomOneOperation, Register, Registry, RemoveOperation,
+    ReplaceOperation, ResolveError, Scope, ScopeData, ScriptBundle, ScriptCache, ScriptComponent,
+    ScriptInstance, ScriptLoadFailed, ScriptLoadProgress, ScriptLoaded, ScriptResolver,
+    ScriptState, ScriptValues, ScriptsReady, SeqOperation, SetOperation, SpawnOperation, Tags,
+    TimedOperations, ToggleOperation, UnloadEntities, UnloadScript, UnresolvedScript,
 };
 use bevy::{
-    app::{Plugin, Update},
-    asset::{AssetServer, Assets},
+    app::{Plugin, PostUpdate, Update},
+    asset::{AssetApp, AssetEvent, AssetServer, Assets, LoadedFolder, UntypedAssetLoadFailedEvent},
     ecs::{
         component::Component,
         entity::Entity,
-        event::{EventReader, EventWriter},
-        query::{Changed, With},
-        schedule::{NextState, State},
+        event::{Event, EventReader, EventWriter},
+        query::{Changed, Has, Or, With},
+        schedule::{IntoSystemConfigs, NextState, State},
         system::{Commands, Query, Res, ResMut},
-        world::Ref,
+        world::{EntityWorldMut, Ref, World},
     },
+    log::error,
     prelude::App,
-    utils::hashbrown::HashMap,
+    time::Time,
 };
 use bevy_common_assets::json::JsonAssetPlugin;
+use serde::Deserialize;
 use serde_json::Value;
 use std::{
+    any::TypeId,
+    collections::HashSet,
     ops::{Deref, DerefMut},
     sync::Arc,
 };
@@ -33,6 +46,7 @@ type SystemFn = Arc<dyn Fn(&mut App) + Send + Sync>;
 pub struct ScriptPlugin {
     registry: Registry,
     add_system_fns: Vec<SystemFn>,
+    resolver: Option<ScriptResolver>,
 }
 
 impl ScriptPlugin {
@@ -40,6 +54,7 @@ impl ScriptPlugin {
         Self {
             registry: Registry::default(),
             add_system_fns: Vec::new(),
+            resolver: None,
         }
     }
 
@@ -53,6 +68,39 @@ impl ScriptPlugin {
                 data.register::<C>(registry, asset_server, entity_commands);
             }),
         );
+        self.registry.remove_fns.insert(
+            id.clone(),
+            Arc::new(|entity_commands| {
+                entity_commands.remove::<C>();
+            }),
+        );
+        self.registry
+            .has_fns
+            .insert(id.clone(), Arc::new(|entity| entity.contains::<C>()));
+        let key = id.clone();
+        self.registry.check_fns.insert(
+            id.clone(),
+            Arc::new(move |value, _registry, capabilities| {
+                capabilities.check_component(&key)?;
+                parse::<C::Data>(value)?;
+                Ok(())
+            }),
+        );
+        self.registry.snapshot_fns.insert(
+            id.clone(),
+            Arc::new(|world, entity, spawned| {
+                let component = world.get::<C>(entity)?;
+                component
+                    .snapshot()
+                    .or_else(|| spawned.cloned())
+                    .or_else(|| {
+                        // Components added without a value, like markers, can be added the same way.
+                        serde_json::from_value::<C::Data>(Value::Null)
+                            .is_ok()
+                            .then_some(Value::Null)
+                    })
+            }),
+        );
         self.registry.add_dependency::<C>(id);
 
         self
@@ -71,6 +119,51 @@ impl ScriptPlugin {
                 data.register::<C>(registry, asset_server, entity_commands);
             }),
         );
+        self.registry.remove_fns.insert(
+            id.clone(),
+            Arc::new(|entity_commands| {
+                Scope::<C>::remove(entity_commands);
+                entity_commands.remove::<C>();
+            }),
+        );
+        self.registry
+            .has_fns
+            .insert(id.clone(), Arc::new(|entity| entity.contains::<C>()));
+        let key = id.clone();
+        self.registry.value_fns.insert(
+            id.clone(),
+            Arc::new(move |entity| {
+                // The component only changes once expressions run, so run this one now.
+                let base = match entity.get::<ScopeData>().and_then(|s| s.run::<C>()) {
+                    Some(StaticExpr::Number(base)) => base,
+                    _ => return entity.get::<C>().map(|component| **component),
+                };
+                let modifiers = entity.get::<Modifiers>();
+                Some(modifiers.map_or(base, |modifiers| modifiers.apply(&key, base)))
+            }),
+        );
+        let key = id.clone();
+        self.registry.check_fns.insert(
+            id.clone(),
+            Arc::new(move |value, registry, capabilities| {
+                capabilities.check_component(&key)?;
+                parse::<C::Data>(value)?;
+                registry.check_expr(value, capabilities)
+            }),
+        );
+        self.registry.snapshot_fns.insert(
+            id.clone(),
+            Arc::new(|world, entity, _spawned| {
+                // Keep the current expression rather than its result.
+                if let Some(source) = world.get::<ScopeData>(entity).and_then(|s| s.source::<C>()) {
+                    return serde_json::to_value(source).ok();
+                }
+                world
+                    .get::<C>(entity)
+                    .map(|component| Value::from(**component))
+            }),
+        );
+        self.registry.stats.insert(TypeId::of::<C>(), id.clone());
         self.registry.add_dependency::<C>(id);
 
         self.add_system_fns.push(Arc::new(|app: &mut App| {
@@ -83,24 +176,72 @@ impl ScriptPlugin {
     pub fn with_event<C: Component + Default>(mut self, id: impl Into<String>) -> Self {
         let id = id.into();
 
+        let key = id.clone();
         self.registry.spawn_fns.insert(
             id.clone(),
-            Arc::new(|value, registry, asset_server, entity_commands| {
-                let operations: HashMap<String, Value> = serde_json::from_value(value).unwrap();
-
-                for (id, operation_value) in operations {
-                    registry.operations.get(&id).unwrap().spawn(
-                        registry,
-                        asset_server,
-                        entity_commands,
-                        operation_value,
-                    );
-                }
+            Arc::new(move |value, registry, asset_server, entity_commands| {
+                let operations = serde_json::from_value(value).unwrap();
+                registry.run_operations(asset_server, entity_commands, operations);
+                TimedOperations::set_trigger(entity_commands, key.clone());
+
+                let key = key.clone();
+                entity_commands.add(move |mut entity: EntityWorldMut| {
+                    if let Some(mut fired) = entity.get_mut::<FiredEvents>() {
+                        fired.0.insert(key);
+                    } else {
+                        entity.insert(FiredEvents(HashSet::from([key])));
+                    }
+                });
+            }),
+        );
+        self.registry.snapshot_fns.insert(
+            id.clone(),
+            Arc::new(|_world, _entity, spawned| spawned.cloned()),
+        );
+        self.registry.check_fns.insert(
+            id.clone(),
+            Arc::new(|value, registry, capabilities| {
+                registry.check_operations(value, capabilities)
+            }),
+        );
+        self.registry.events.insert(TypeId::of::<C>(), id);
+
+        self.add_system_fns.push(Arc::new(|app: &mut App| {
+            app.add_systems(
+                Update,
+                (run_events::<C>, timed::cancel_timed_operations::<C>),
+            );
+        }));
+
+        self
+    }
+
+    pub fn with_sent_event<E: Event + for<'de> Deserialize<'de>>(
+        mut self,
+        id: impl Into<String>,
+    ) -> Self {
+        let id = id.into();
+
+        let key = id.clone();
+        self.registry.send_fns.insert(
+            id,
+            Arc::new(move |value, commands| {
+                // Payloads can hold expressions, so they're only known once they run.
+                let event: E = match serde_json::from_value(value) {
+                    Ok(event) => event,
+                    Err(error) => {
+                        error!("Failed to emit `{key}`: {error}");
+                        return;
+                    }
+                };
+                commands.add(move |world: &mut World| {
+                    world.send_event(event);
+                });
             }),
         );
 
         self.add_system_fns.push(Arc::new(|app: &mut App| {
-            app.add_systems(Update, run_events::<C>);
+            app.add_event::<E>();
         }));
 
         self
@@ -121,6 +262,21 @@ impl ScriptPlugin {
             .insert(id.into(), Arc::new(operation));
         self
     }
+
+    pub fn with_migration(
+        mut self,
+        from: u32,
+        to: u32,
+        f: impl Fn(&mut ComponentsData) + Send + Sync + 'static,
+    ) -> Self {
+        self.registry.add_migration(from, to, f);
+        self
+    }
+
+    pub fn with_resolver(mut self, resolver: ScriptResolver) -> Self {
+        self.resolver = Some(resolver);
+        self
+    }
 }
 
 impl Default for ScriptPlugin {
@@ -130,19 +286,69 @@ impl Default for ScriptPlugin {
             .with_function("+", function::add())
             .with_function("-", function::sub())
             .with_function("/", function::div())
+            .with_function("<", function::lt())
+            .with_function("<=", function::le())
+            .with_function(">", function::gt())
+            .with_function(">=", function::ge())
+            .with_function("==", function::eq())
+            .with_function("!=", function::ne())
+            .with_component::<Modifiers>("modifiers")
+            .with_component::<Tags>("tags")
             .with_operation("add", AddOperation)
+            .with_operation("remove", RemoveOperation)
+            .with_operation("set", SetOperation)
+            .with_operation("toggle", ToggleOperation)
+            .with_operation("replace", ReplaceOperation)
+            .with_operation("spawn", SpawnOperation)
+            .with_operation("despawn", DespawnOperation)
+            .with_operation("emit", EmitOperation)
+            .with_operation("modify", ModifyOperation)
+            .with_operation("if", IfOperation)
+            .with_operation("seq", SeqOperation)
+            .with_operation("random_one", RandomOneOperation)
+            .with_operation("after", AfterOperation)
+            .with_operation("every", EveryOperation)
+            .with_operation("for", ForOperation)
     }
 }
 
 impl Plugin for ScriptPlugin {
     fn build(&self, app: &mut App) {
-        app.add_plugins(JsonAssetPlugin::<ComponentsData>::new(&[]))
+        app.add_plugins(JsonAssetPlugin::<ComponentsData>::new(&["script.json"]))
+            .init_asset::<ScriptCache>()
+            .register_asset_loader(ScriptCacheLoader)
+            .register_type::<ScriptBundle>()
+            .register_type::<ScriptValues>()
             .insert_resource(self.registry.clone())
             .init_resource::<AssetRegistry>()
             .insert_state(ScriptState::Ready)
             .add_event::<LoadScript>()
+            .add_event::<LoadScriptFolder>()
+            .add_event::<LoadScriptPack>()
+            .add_event::<LoadScriptCache>()
+            .add_event::<UnloadScript>()
             .add_event::<ScriptsReady>()
-            .add_systems(Update, (load_assets, spawn_expr));
+            .add_event::<UnresolvedScript>()
+            .add_event::<ScriptLoaded>()
+            .add_event::<ScriptLoadFailed>()
+            .init_resource::<ScriptLoadProgress>()
+            .add_systems(
+                Update,
+                (load_assets, reload_assets, unload_assets, spawn_expr).chain(),
+            )
+            .add_systems(
+                Update,
+                (
+                    modifier::expire_modifiers,
+                    modifier::remove_modifiers,
+                    timed::run_timed_operations,
+                ),
+            )
+            .add_systems(PostUpdate, operation::despawn_pending);
+
+        if let Some(resolver) = &self.resolver {
+            app.insert_resource(resolver.clone());
+        }
 
         for f in &self.add_system_fns {
             f(app)
@@ -150,22 +356,418 @@ impl Plugin for ScriptPlugin {
     }
 }
 
+#[allow(clippy::too_many_arguments)]
 fn load_assets(
     mut asset_registry: ResMut<AssetRegistry>,
+    registry: Res<Registry>,
     asset_server: Res<AssetServer>,
+    mut assets: ResMut<Assets<ComponentsData>>,
+    folders: Res<Assets<LoadedFolder>>,
+    caches: Res<Assets<ScriptCache>>,
     mut asset_events: EventReader<LoadScript>,
+    mut folder_events: EventReader<LoadScriptFolder>,
+    mut pack_events: EventReader<LoadScriptPack>,
+    mut cache_events: EventReader<LoadScriptCache>,
+    mut failed_events: EventReader<UntypedAssetLoadFailedEvent>,
+    mut loaded_writer: EventWriter<ScriptLoaded>,
+    mut failed_writer: EventWriter<ScriptLoadFailed>,
+    mut progress: ResMut<ScriptLoadProgress>,
     mut state: ResMut<NextState<ScriptState>>,
 ) {
     for event in asset_events.read() {
+        if asset_registry.is_idle() {
+            *progress = ScriptLoadProgress::default();
+        }
+
         let handle = asset_server.load(event.path.clone());
 
         // TODO path or string?
-        asset_registry
-            .pending_handles
-            .insert(event.path.to_string_lossy().to_string(), handle);
+        PendingLoad::push(
+            &mut asset_registry.pending_handles,
+            event.path.to_string_lossy().to_string(),
+            event.ticket,
+            handle,
+            None,
+        );
+        progress.total += 1;
+
+        state.set(ScriptState::Loading);
+    }
+
+    for event in folder_events.read() {
+        if asset_registry.is_idle() {
+            *progress = ScriptLoadProgress::default();
+        }
+
+        let handle = asset_server.load_folder(event.path.clone());
+
+        // Count the folder as a single script until its contents are known.
+        PendingLoad::push(
+            &mut asset_registry.pending_folders,
+            event.path.to_string_lossy().to_string(),
+            event.ticket,
+            handle,
+            None,
+        );
+        progress.total += 1;
+
+        state.set(ScriptState::Loading);
+    }
+
+    for event in pack_events.read() {
+        if asset_registry.is_idle() {
+            *progress = ScriptLoadProgress::default();
+        }
+
+        let pack = asset_registry.pack_index(&event.name);
+        asset_registry.packs[pack].capabilities = event.capabilities.clone();
+        let handle = asset_server.load_folder(event.path.clone());
+
+        PendingLoad::push(
+            &mut asset_registry.pending_folders,
+            event.path.to_string_lossy().to_string(),
+            event.ticket,
+            handle,
+            Some(pack),
+        );
+        progress.total += 1;
+
+        state.set(ScriptState::Loading);
+    }
+
+    for event in cache_events.read() {
+        if asset_registry.is_idle() {
+            *progress = ScriptLoadProgress::default();
+        }
+
+        let handle = asset_server.load(event.path.clone());
+
+        PendingLoad::push(
+            &mut asset_registry.pending_caches,
+            event.path.to_string_lossy().to_string(),
+            event.ticket,
+            handle,
+            None,
+        );
+        progress.total += 1;
 
         state.set(ScriptState::Loading);
     }
+
+    for event in failed_events.read() {
+        let failed_path = asset_registry
+            .pending_handles
+            .iter()
+            .find(|(_, pending)| pending.handle.id().untyped() == event.id)
+            .map(|(path, _)| path.clone());
+        let failed_folder = asset_registry
+            .pending_folders
+            .iter()
+            .find(|(_, pending)| pending.handle.id().untyped() == event.id)
+            .map(|(path, _)| path.clone());
+        let failed_cache = asset_registry
+            .pending_caches
+            .iter()
+            .find(|(_, pending)| pending.handle.id().untyped() == event.id)
+            .map(|(path, _)| path.clone());
+
+        let tickets = if let Some(path) = failed_path {
+            asset_registry
+                .pending_handles
+                .remove(&path)
+                .unwrap()
+                .tickets
+        } else if let Some(path) = failed_folder {
+            asset_registry
+                .pending_folders
+                .remove(&path)
+                .unwrap()
+                .tickets
+        } else if let Some(path) = failed_cache {
+            asset_registry.pending_caches.remove(&path).unwrap().tickets
+        } else {
+            continue;
+        };
+
+        for ticket in tickets {
+            progress.failed += 1;
+            failed_writer.send(ScriptLoadFailed {
+                ticket,
+                path: event.path.to_string(),
+                error: event.error.to_string(),
+            });
+        }
+    }
+
+    let mut ready_folders = Vec::new();
+    for (path, pending) in &asset_registry.pending_folders {
+        if let Some(folder) = folders.get(&pending.handle) {
+            ready_folders.push((path.clone(), folder.handles.clone()));
+        }
+    }
+
+    for (path, handles) in ready_folders {
+        let PendingLoad { tickets, pack, .. } =
+            asset_registry.pending_folders.remove(&path).unwrap();
+        progress.total -= tickets.len();
+
+        for handle in handles {
+            let Some(asset_path) = handle.path().map(ToString::to_string) else {
+                continue;
+            };
+
+            // Skip any other assets that share the folder.
+            let Ok(handle) = handle.try_typed::<ComponentsData>() else {
+                continue;
+            };
+
+            for ticket in &tickets {
+                PendingLoad::push(
+                    &mut asset_registry.pending_handles,
+                    asset_path.clone(),
+                    *ticket,
+                    handle.clone(),
+                    pack,
+                );
+                progress.total += 1;
+            }
+        }
+    }
+
+    let ready_caches: Vec<_> = asset_registry
+        .pending_caches
+        .iter()
+        .filter(|(_, pending)| caches.contains(&pending.handle))
+        .map(|(path, _)| path.clone())
+        .collect();
+
+    for path in ready_caches {
+        let PendingLoad {
+            tickets, handle, ..
+        } = asset_registry.pending_caches.remove(&path).unwrap();
+        let scripts = caches.get(&handle).unwrap();
+
+        let checksum = registry.checksum();
+        if scripts.checksum != checksum {
+            let error = CacheError::Checksum {
+                expected: checksum,
+                found: scripts.checksum,
+            };
+            for ticket in tickets {
+                progress.failed += 1;
+                failed_writer.send(ScriptLoadFailed {
+                    ticket,
+                    path: path.clone(),
+                    error: error.to_string(),
+                });
+            }
+            continue;
+        }
+
+        for (id, data) in scripts.scripts() {
+            // Cached scripts are registered like loaded ones, so they can be unloaded and
+            // resolved again.
+            let handle = assets.add(data);
+            asset_registry.requested.remove(&id);
+            asset_registry.handles.insert(id.clone(), handle.clone());
+            asset_registry.invalidate(handle.id());
+
+            for ticket in &tickets {
+                *asset_registry.ref_counts.entry(id.clone()).or_default() += 1;
+
+                loaded_writer.send(ScriptLoaded {
+                    ticket: *ticket,
+                    id: id.clone(),
+                    path: path.clone(),
+                });
+            }
+        }
+        progress.loaded += tickets.len();
+    }
+
+    let mut ready_handles = Vec::new();
+    for (path, pending) in &asset_registry.pending_handles {
+        if !assets.contains(&pending.handle) {
+            continue;
+        }
+
+        let migrated = match registry.needs_migration(assets.get(&pending.handle).unwrap()) {
+            Ok(true) => registry.migrate(assets.get_mut(&pending.handle).unwrap()),
+            Ok(false) => Ok(()),
+            Err(error) => Err(error),
+        };
+
+        let data = assets.get(&pending.handle).unwrap();
+        let id = data.0.get("id").and_then(Value::as_str).map(str::to_owned);
+        let rejected = match migrated {
+            Ok(()) => match pending.pack {
+                Some(pack) => {
+                    let pack = &asset_registry.packs[pack];
+                    registry
+                        .check(data, &pack.capabilities)
+                        .err()
+                        .map(|error| format!("Rejected by pack `{}`: {error}", pack.name))
+                }
+                // Scripts outside of packs can do anything, as long as it's registered.
+                None => registry
+                    .check(data, &Capabilities::all())
+                    .err()
+                    .map(|error| error.to_string()),
+            },
+            Err(error) => Some(error.to_string()),
+        };
+        ready_handles.push((path.clone(), id, rejected));
+    }
+
+    for (path, id, rejected) in ready_handles {
+        let PendingLoad {
+            tickets,
+            handle,
+            pack,
+        } = asset_registry.pending_handles.remove(&path).unwrap();
+
+        let Some(id) = id else {
+            for ticket in tickets {
+                progress.failed += 1;
+                failed_writer.send(ScriptLoadFailed {
+                    ticket,
+                    path: path.clone(),
+                    error: String::from("Expected a string `id`."),
+                });
+            }
+            continue;
+        };
+
+        if let Some(error) = rejected {
+            for ticket in tickets {
+                progress.failed += 1;
+                failed_writer.send(ScriptLoadFailed {
+                    ticket,
+                    path: path.clone(),
+                    error: error.clone(),
+                });
+            }
+            continue;
+        }
+
+        asset_registry.requested.remove(&id);
+        if let Some(pack) = pack {
+            asset_registry.packs[pack]
+                .handles
+                .insert(id.clone(), handle.clone());
+        } else {
+            asset_registry.handles.insert(id.clone(), handle.clone());
+        }
+        asset_registry.invalidate(handle.id());
+
+        for ticket in tickets {
+            *asset_registry.ref_counts.entry(id.clone()).or_default() += 1;
+
+            progress.loaded += 1;
+            loaded_writer.send(ScriptLoaded {
+                ticket,
+                id: id.clone(),
+                path: path.clone(),
+            });
+        }
+    }
+}
+
+fn reload_assets(
+    mut asset_registry: ResMut<AssetRegistry>,
+    registry: Res<Registry>,
+    mut assets: ResMut<Assets<ComponentsData>>,
+    mut asset_events: EventReader<AssetEvent<ComponentsData>>,
+) {
+    for event in asset_events.read() {
+        if let AssetEvent::Modified { id } = event {
+            asset_registry.invalidate(*id);
+
+            let Some(data) = assets.get(*id) else {
+                continue;
+            };
+
+            // Migrating modifies the asset again, so only touch it when there is work to do.
+            match registry.needs_migration(data) {
+                Ok(true) => {
+                    if let Err(error) = registry.migrate(assets.get_mut(*id).unwrap()) {
+                        error!("Failed to migrate reloaded script: {error}");
+                    }
+                    continue;
+                }
+                Ok(false) => {}
+                Err(error) => {
+                    error!("Failed to migrate reloaded script: {error}");
+                    continue;
+                }
+            }
+
+            // Reloaded pack scripts are held to the same capabilities as the first load.
+            for pack in &mut asset_registry.packs {
+                if let Err(error) = registry.check(data, &pack.capabilities) {
+                    pack.handles.retain(|script, handle| {
+                        if handle.id() != *id {
+                            return true;
+                        }
+
+                        error!(
+                            "Rejected script `{script}` from pack `{}`: {error}",
+                            pack.name
+                        );
+                        false
+                    });
+                }
+            }
+            if let Err(error) = registry.check(data, &Capabilities::all()) {
+                asset_registry.handles.retain(|script, handle| {
+                    if handle.id() != *id {
+                        return true;
+                    }
+
+                    error!("Rejected script `{script}`: {error}");
+                    false
+                });
+            }
+        }
+    }
+}
+
+fn unload_assets(
+    mut commands: Commands,
+    mut asset_registry: ResMut<AssetRegistry>,
+    mut unload_events: EventReader<UnloadScript>,
+    query: Query<(Entity, &ScriptInstance)>,
+) {
+    for event in unload_events.read() {
+        let Some(count) = asset_registry.ref_counts.get_mut(&event.id) else {
+            continue;
+        };
+
+        // Keep shared scripts until every load has been released.
+        *count -= 1;
+        if *count > 0 {
+            continue;
+        }
+
+        asset_registry.ref_counts.remove(&event.id);
+        asset_registry.handles.remove(&event.id);
+        for pack in &mut asset_registry.packs {
+            pack.handles.remove(&event.id);
+        }
+        asset_registry.invalidate_id(&event.id);
+
+        for (entity, instance) in &query {
+            if instance.id != event.id {
+                continue;
+            }
+
+            match event.entities {
+                UnloadEntities::Keep => {}
+                UnloadEntities::Despawn => commands.add(DespawnScript(entity)),
+                UnloadEntities::Strip => commands.add(DetachScript(entity)),
+            }
+        }
+    }
 }
 
 #[allow(clippy::too_many_arguments)]
@@ -173,60 +775,123 @@ fn spawn_expr(
     mut commands: Commands,
     asset_server: Res<AssetServer>,
     mut asset_registry: ResMut<AssetRegistry>,
-    mut assets: ResMut<Assets<ComponentsData>>,
+    assets: Res<Assets<ComponentsData>>,
     registry: Res<Registry>,
-    query: Query<(Entity, &ScriptBundle)>,
+    query: Query<(Entity, &ScriptBundle, Has<ScriptInstance>)>,
     mut asset_events: EventWriter<ScriptsReady>,
+    mut unresolved_events: EventWriter<UnresolvedScript>,
     state: Res<State<ScriptState>>,
     mut next_state: ResMut<NextState<ScriptState>>,
+    resolver: Option<Res<ScriptResolver>>,
+    mut progress: ResMut<ScriptLoadProgress>,
+    time: Res<Time>,
 ) {
-    let mut ready_handles = Vec::new();
-    for (path, handle) in &asset_registry.pending_handles {
-        if let Some(data) = assets.get_mut(handle) {
-            let id = data.0.remove("id").unwrap();
-            let id_s = id.as_str().unwrap().to_owned();
-
-            ready_handles.push((path.clone(), id_s, handle.clone()));
-        }
-    }
-
-    for (path, id, handle) in ready_handles {
-        asset_registry.pending_handles.remove(&path);
-        asset_registry.handles.insert(id, handle);
-    }
+    let mut is_ready = asset_registry.is_idle();
+    let mut timed_out = Vec::new();
+    for (entity, bundle, is_scripted) in &query {
+        match bundle.resolve(&mut asset_registry, &registry, &assets) {
+            Ok(data) => {
+                bundle.apply(
+                    &mut commands,
+                    &registry,
+                    &asset_server,
+                    entity,
+                    data,
+                    is_scripted,
+                );
+            }
+            Err(ResolveError::Missing(id)) => {
+                // Without a resolver, the id can only come from a load that is still pending.
+                let is_unresolved = if let Some(resolver) = &resolver {
+                    if let Some(requested) = asset_registry.requested.get(&id) {
+                        time.elapsed() - *requested > resolver.timeout
+                    } else if let Some(path) = resolver.path(&id) {
+                        if asset_registry.is_idle() {
+                            *progress = ScriptLoadProgress::default();
+                        }
+
+                        let handle = asset_server.load(path.clone());
+                        PendingLoad::push(
+                            &mut asset_registry.pending_handles,
+                            path.to_string_lossy().to_string(),
+                            LoadTicket::next(),
+                            handle,
+                            None,
+                        );
+                        asset_registry.requested.insert(id.clone(), time.elapsed());
+                        progress.total += 1;
+
+                        next_state.set(ScriptState::Loading);
+                        false
+                    } else {
+                        true
+                    }
+                } else {
+                    asset_registry.is_idle()
+                };
+
+                if is_unresolved {
+                    error!(
+                        "Failed to spawn script `{}`: `{id}` was never resolved.",
+                        bundle.id
+                    );
 
-    let mut is_ready = true;
-    for (entity, bundle) in &query {
-        if let Some(handle) = asset_registry.handles.get(&bundle.0) {
-            if let Some(data) = assets.get(handle) {
-                registry.spawn(&asset_server, &mut commands.entity(entity), data.0.clone());
+                    unresolved_events.send(UnresolvedScript {
+                        entity,
+                        id: id.clone(),
+                    });
+                    timed_out.push(id);
+                    commands.entity(entity).remove::<ScriptBundle>();
+                } else {
+                    is_ready = false;
+                }
+            }
+            Err(ResolveError::Loading(_)) => is_ready = false,
+            Err(error) => {
+                error!("Failed to spawn script `{}`: {error}", bundle.id);
 
                 commands.entity(entity).remove::<ScriptBundle>();
-            } else {
-                is_ready = false;
             }
         }
     }
 
+    // Every bundle waiting on a timed out id has been reported, so a later bundle can request it again.
+    for id in timed_out {
+        asset_registry.requested.remove(&id);
+    }
+
     if is_ready && *state == ScriptState::Loading {
-        next_state.set(ScriptState::Ready);
-        asset_events.send(ScriptsReady);
+        if progress.failed > 0 {
+            next_state.set(ScriptState::Failed);
+        } else {
+            next_state.set(ScriptState::Ready);
+            asset_events.send(ScriptsReady);
+        }
     }
 }
 
 type ExprQuery<'w, 's, T> = Query<
     'w,
     's,
-    (Entity, Option<&'static mut T>, &'static ScopeData),
-    (With<Scope<T>>, Changed<ScopeData>),
+    (
+        Entity,
+        Option<&'static mut T>,
+        &'static ScopeData,
+        Option<&'static Modifiers>,
+    ),
+    (With<Scope<T>>, Or<(Changed<ScopeData>, Changed<Modifiers>)>),
 >;
 
-fn run_expr<T>(mut commands: Commands, mut query: ExprQuery<T>)
+fn run_expr<T>(mut commands: Commands, registry: Res<Registry>, mut query: ExprQuery<T>)
 where
     T: Component + Default + DerefMut<Target = f64>,
 {
-    for (entity, value, scope_data) in &mut query {
-        if let Some(StaticExpr::Number(new)) = scope_data.run() {
+    let stat = &registry.stats[&TypeId::of::<T>()];
+
+    for (entity, value, scope_data, modifiers) in &mut query {
+        if let Some(StaticExpr::Number(base)) = scope_data.run::<T>() {
+            let new = modifiers.map_or(base, |modifiers| modifiers.apply(stat, base));
+
             if let Some(mut v) = value {
                 if **v != new {
                     **v = new;
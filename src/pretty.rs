@@ -0,0 +1,138 @@
+use crate::ComponentsData;
+use serde_json::{Map, Value};
+
+const LEADING_KEYS: [&str; 5] = ["id", "version", "extends", "params", "remove"];
+
+// Objects get one key per line in a fixed order, while expressions and other arrays
+// without objects stay on a single line.
+pub fn to_pretty_string(value: &Value) -> String {
+    let mut out = String::new();
+    write_value(&mut out, value, 0);
+    out.push('\n');
+    out
+}
+
+impl ComponentsData {
+    pub fn to_pretty_string(&self) -> String {
+        let values: Map<_, _> = self
+            .0
+            .iter()
+            .map(|(key, value)| (key.clone(), value.clone()))
+            .collect();
+        to_pretty_string(&Value::Object(values))
+    }
+}
+
+fn write_value(out: &mut String, value: &Value, indent: usize) {
+    match value {
+        Value::Object(map) if !map.is_empty() => {
+            out.push_str("{\n");
+            let keys = sorted_keys(map);
+            for (idx, key) in keys.iter().enumerate() {
+                push_indent(out, indent + 1);
+                out.push_str(&serde_json::to_string(key).unwrap());
+                out.push_str(": ");
+                write_value(out, &map[*key], indent + 1);
+                if idx + 1 < keys.len() {
+                    out.push(',');
+                }
+                out.push('\n');
+            }
+            push_indent(out, indent);
+            out.push('}');
+        }
+        Value::Array(items) if items.iter().any(contains_object) => {
+            out.push_str("[\n");
+            for (idx, item) in items.iter().enumerate() {
+                push_indent(out, indent + 1);
+                write_value(out, item, indent + 1);
+                if idx + 1 < items.len() {
+                    out.push(',');
+                }
+                out.push('\n');
+            }
+            push_indent(out, indent);
+            out.push(']');
+        }
+        Value::Array(items) => {
+            out.push('[');
+            for (idx, item) in items.iter().enumerate() {
+                if idx > 0 {
+                    out.push_str(", ");
+                }
+                write_value(out, item, indent);
+            }
+            out.push(']');
+        }
+        _ => out.push_str(&serde_json::to_string(value).unwrap()),
+    }
+}
+
+fn sorted_keys(map: &Map<String, Value>) -> Vec<&String> {
+    let mut keys: Vec<_> = map.keys().collect();
+    keys.sort_by_key(|key| {
+        let leading = LEADING_KEYS.iter().position(|leading| leading == key);
+        (leading.unwrap_or(LEADING_KEYS.len()), *key)
+    });
+    keys
+}
+
+fn contains_object(value: &Value) -> bool {
+    match value {
+        Value::Object(map) => !map.is_empty(),
+        Value::Array(items) => items.iter().any(contains_object),
+        _ => false,
+    }
+}
+
+fn push_indent(out: &mut String, indent: usize) {
+    for _ in 0..indent {
+        out.push_str("  ");
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use serde_json::json;
+
+    const SWORD: &str = r#"{
+  "id": "sword",
+  "extends": "base",
+  "damage": ["+", 1, ["@", "durability"]],
+  "durability": 2,
+  "on_equip": {
+    "add": "invincible",
+    "after": [
+      0.5,
+      {
+        "remove": "invincible"
+      }
+    ]
+  }
+}
+"#;
+
+    #[test]
+    fn layout() {
+        let data: ComponentsData = serde_json::from_value(json!({
+            "on_equip": {"after": [0.5, {"remove": "invincible"}], "add": "invincible"},
+            "durability": 2,
+            "damage": ["+", 1, ["@", "durability"]],
+            "extends": "base",
+            "id": "sword",
+        }))
+        .unwrap();
+        assert_eq!(data.to_pretty_string(), SWORD);
+    }
+
+    #[test]
+    fn stable() {
+        let data: ComponentsData = serde_json::from_str(SWORD).unwrap();
+        let text = data.to_pretty_string();
+        assert_eq!(text, SWORD);
+
+        let data: ComponentsData = serde_json::from_str(&text).unwrap();
+        assert_eq!(data.to_pretty_string(), text);
+    }
+}
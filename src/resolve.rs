@@ -0,0 +1,375 @@
+use crate::{pack::Sources, AssetRegistry, CapabilityError, ComponentsData};
+use bevy::asset::{AssetId, Assets};
+use serde_json::{Map, Value};
+use std::{collections::HashMap, fmt};
+
+#[derive(Clone, Debug, PartialEq)]
+pub enum ResolveError {
+    Missing(String),
+    Loading(String),
+    Cycle(Vec<String>),
+    InvalidExtends(String),
+    InvalidRemove(String),
+    InvalidParams(String),
+    MissingParam(String),
+    UnknownParam(String),
+    InvalidPatch(String, String),
+    Rejected(String, CapabilityError),
+}
+
+impl fmt::Display for ResolveError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            ResolveError::Missing(id) => write!(f, "Script `{id}` is not loaded."),
+            ResolveError::Loading(id) => write!(f, "Script `{id}` is still loading."),
+            ResolveError::Cycle(ids) => write!(f, "Inheritance cycle: {}.", ids.join(" -> ")),
+            ResolveError::InvalidExtends(id) => {
+                write!(f, "Expected a script id for `extends` in `{id}`.")
+            }
+            ResolveError::InvalidRemove(id) => {
+                write!(f, "Expected a list of keys for `remove` in `{id}`.")
+            }
+            ResolveError::InvalidParams(id) => {
+                write!(f, "Expected a list of names for `params` in `{id}`.")
+            }
+            ResolveError::MissingParam(param) => write!(f, "Missing parameter `{param}`."),
+            ResolveError::UnknownParam(param) => write!(f, "Unknown parameter `{param}`."),
+            ResolveError::InvalidPatch(id, error) => write!(f, "Failed to patch `{id}`: {error}"),
+            ResolveError::Rejected(id, error) => write!(f, "Rejected `{id}`: {error}"),
+        }
+    }
+}
+
+impl std::error::Error for ResolveError {}
+
+#[derive(Clone)]
+pub(crate) struct ResolvedScript {
+    data: ComponentsData,
+    sources: Sources,
+    chain: Vec<String>,
+}
+
+impl AssetRegistry {
+    pub fn resolve(
+        &mut self,
+        id: &str,
+        assets: &Assets<ComponentsData>,
+    ) -> Result<ComponentsData, ResolveError> {
+        self.resolve_script(id, assets)
+            .map(|resolved| resolved.data.clone())
+    }
+
+    pub fn provenance(
+        &mut self,
+        id: &str,
+        assets: &Assets<ComponentsData>,
+    ) -> Result<HashMap<String, Option<String>>, ResolveError> {
+        self.resolve_script(id, assets)
+            .map(|resolved| resolved.sources.clone())
+    }
+
+    fn resolve_script(
+        &mut self,
+        id: &str,
+        assets: &Assets<ComponentsData>,
+    ) -> Result<&ResolvedScript, ResolveError> {
+        if !self.resolved.contains_key(id) {
+            let mut chain = Vec::new();
+            let (values, sources) = self.resolve_values(id, assets, &mut chain)?;
+
+            self.resolved.insert(
+                id.to_owned(),
+                ResolvedScript {
+                    data: ComponentsData(values),
+                    sources,
+                    chain,
+                },
+            );
+        }
+
+        Ok(&self.resolved[id])
+    }
+
+    pub(crate) fn invalidate(&mut self, asset_id: AssetId<ComponentsData>) {
+        let ids: Vec<_> = self
+            .all_handles()
+            .filter(|(_, handle)| handle.id() == asset_id)
+            .map(|(id, _)| id.clone())
+            .collect();
+
+        for id in ids {
+            self.invalidate_id(&id);
+        }
+    }
+
+    pub(crate) fn invalidate_id(&mut self, id: &str) {
+        // Drop the script itself and every script that extends it.
+        self.resolved
+            .retain(|_, resolved| !resolved.chain.iter().any(|parent| parent == id));
+    }
+
+    fn resolve_values(
+        &self,
+        id: &str,
+        assets: &Assets<ComponentsData>,
+        chain: &mut Vec<String>,
+    ) -> Result<(HashMap<String, Value>, Sources), ResolveError> {
+        if let Some(idx) = chain.iter().position(|parent| parent == id) {
+            let mut cycle = chain[idx..].to_vec();
+            cycle.push(id.to_owned());
+            return Err(ResolveError::Cycle(cycle));
+        }
+
+        let (mut values, mut own_sources) = self.layered(id, assets)?;
+
+        chain.push(id.to_owned());
+
+        values.remove("id");
+        values.remove("version");
+
+        let removed = match values.remove("remove") {
+            Some(Value::Array(keys)) => keys
+                .into_iter()
+                .map(|key| match key {
+                    Value::String(key) => Ok(key),
+                    _ => Err(ResolveError::InvalidRemove(id.to_owned())),
+                })
+                .collect::<Result<Vec<_>, _>>()?,
+            Some(_) => return Err(ResolveError::InvalidRemove(id.to_owned())),
+            None => Vec::new(),
+        };
+
+        let (mut resolved, mut sources) = match values.remove("extends") {
+            Some(Value::String(parent)) => self.resolve_values(&parent, assets, chain)?,
+            Some(_) => return Err(ResolveError::InvalidExtends(id.to_owned())),
+            None => Default::default(),
+        };
+
+        for key in &removed {
+            resolved.remove(key);
+            sources.remove(key);
+        }
+
+        own_sources.retain(|key, _| values.contains_key(key));
+        sources.extend(own_sources);
+
+        Ok((ComponentsData(resolved).with_overrides(values).0, sources))
+    }
+}
+
+impl ComponentsData {
+    pub fn with_overrides(mut self, overrides: HashMap<String, Value>) -> Self {
+        for (key, value) in overrides {
+            if let Some(target) = self.0.get_mut(&key) {
+                merge(target, value);
+            } else {
+                self.0.insert(key, value);
+            }
+        }
+
+        self
+    }
+
+    pub fn with_params(
+        mut self,
+        id: &str,
+        params: &HashMap<String, Value>,
+    ) -> Result<Self, ResolveError> {
+        let names = match self.0.remove("params") {
+            Some(Value::Array(names)) => names
+                .into_iter()
+                .map(|name| match name {
+                    Value::String(name) => Ok(name),
+                    _ => Err(ResolveError::InvalidParams(id.to_owned())),
+                })
+                .collect::<Result<Vec<_>, _>>()?,
+            Some(_) => return Err(ResolveError::InvalidParams(id.to_owned())),
+            None => Vec::new(),
+        };
+
+        let mut declared = HashMap::new();
+        for name in names {
+            let value = params
+                .get(&name)
+                .ok_or_else(|| ResolveError::MissingParam(name.clone()))?;
+            declared.insert(name, value.clone());
+        }
+
+        for value in self.0.values_mut() {
+            substitute(value, &declared)?;
+        }
+
+        Ok(self)
+    }
+}
+
+fn substitute(value: &mut Value, params: &HashMap<String, Value>) -> Result<(), ResolveError> {
+    match value {
+        Value::Array(items) => {
+            if let [Value::String(ident), Value::String(name)] = items.as_slice() {
+                if ident == "param" {
+                    *value = params
+                        .get(name)
+                        .cloned()
+                        .ok_or_else(|| ResolveError::UnknownParam(name.clone()))?;
+                    return Ok(());
+                }
+            }
+
+            for item in items {
+                substitute(item, params)?;
+            }
+        }
+        Value::Object(map) => {
+            for item in map.values_mut() {
+                substitute(item, params)?;
+            }
+        }
+        _ => {}
+    }
+
+    Ok(())
+}
+
+pub(crate) fn merge(target: &mut Value, value: Value) {
+    match (target, value) {
+        (Value::Object(target), Value::Object(value)) => merge_map(target, value),
+        (target, value) => *target = value,
+    }
+}
+
+fn merge_map(target: &mut Map<String, Value>, value: Map<String, Value>) {
+    for (key, value) in value {
+        if let Some(target) = target.get_mut(&key) {
+            merge(target, value);
+        } else {
+            target.insert(key, value);
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{Capabilities, ScriptPack};
+    use bevy::asset::Handle;
+    use serde_json::json;
+
+    fn add(
+        assets: &mut Assets<ComponentsData>,
+        handles: &mut HashMap<String, Handle<ComponentsData>>,
+        value: Value,
+    ) {
+        let data: ComponentsData = serde_json::from_value(value).unwrap();
+        let id = data.0["id"].as_str().unwrap().to_owned();
+        handles.insert(id, assets.add(data));
+    }
+
+    #[test]
+    fn cycle() {
+        let mut assets = Assets::default();
+        let mut registry = AssetRegistry::default();
+        add(
+            &mut assets,
+            &mut registry.handles,
+            json!({"id": "a", "extends": "b"}),
+        );
+        add(
+            &mut assets,
+            &mut registry.handles,
+            json!({"id": "b", "extends": "c"}),
+        );
+        add(
+            &mut assets,
+            &mut registry.handles,
+            json!({"id": "c", "extends": "b"}),
+        );
+
+        assert_eq!(
+            registry.resolve("a", &assets).err(),
+            Some(ResolveError::Cycle(vec![
+                String::from("b"),
+                String::from("c"),
+                String::from("b"),
+            ]))
+        );
+    }
+
+    #[test]
+    fn packs() {
+        let mut assets = Assets::default();
+        let mut registry = AssetRegistry::default();
+        add(
+            &mut assets,
+            &mut registry.handles,
+            json!({"id": "sword", "damage": 1, "durability": 5}),
+        );
+        add(
+            &mut assets,
+            &mut registry.handles,
+            json!({"id": "fire_sword", "extends": "sword", "burn": 2}),
+        );
+
+        for (name, layer) in [
+            (
+                "merge",
+                json!({"id": "sword", "merge": {"durability": null, "damage": 2}}),
+            ),
+            (
+                "patch",
+                json!({"id": "sword", "patch": [
+                    {"op": "add", "path": "/weight", "value": 3},
+                    {"op": "replace", "path": "/damage", "value": 4},
+                ]}),
+            ),
+        ] {
+            let mut handles = HashMap::new();
+            add(&mut assets, &mut handles, layer);
+            registry.packs.push(ScriptPack {
+                name: name.to_owned(),
+                handles,
+                capabilities: Capabilities::all(),
+            });
+        }
+
+        let data = registry.resolve("fire_sword", &assets).unwrap();
+        assert_eq!(
+            Value::Object(data.0.into_iter().collect()),
+            json!({"damage": 4, "weight": 3, "burn": 2})
+        );
+
+        let sources = registry.provenance("fire_sword", &assets).unwrap();
+        assert_eq!(sources["damage"].as_deref(), Some("patch"));
+        assert_eq!(sources["weight"].as_deref(), Some("patch"));
+        assert_eq!(sources["burn"], None);
+        assert!(!sources.contains_key("durability"));
+    }
+
+    #[test]
+    fn invalid_patch() {
+        let mut assets = Assets::default();
+        let mut registry = AssetRegistry::default();
+        add(
+            &mut assets,
+            &mut registry.handles,
+            json!({"id": "sword", "damage": 1}),
+        );
+
+        let mut handles = HashMap::new();
+        add(
+            &mut assets,
+            &mut handles,
+            json!({"id": "sword", "patch": [{"op": "remove", "path": "/weight"}]}),
+        );
+        registry.packs.push(ScriptPack {
+            name: String::from("broken"),
+            handles,
+            capabilities: Capabilities::all(),
+        });
+
+        assert!(matches!(
+            registry.resolve("sword", &assets),
+            Err(ResolveError::InvalidPatch(id, _)) if id == "sword"
+        ));
+    }
+}
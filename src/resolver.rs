@@ -0,0 +1,44 @@
+use bevy::ecs::{entity::Entity, event::Event, system::Resource};
+use std::{collections::HashMap, path::PathBuf, sync::Arc, time::Duration};
+
+type ResolveFn = Arc<dyn Fn(&str) -> Option<PathBuf> + Send + Sync>;
+
+#[derive(Clone, Resource)]
+pub struct ScriptResolver {
+    resolve_fn: ResolveFn,
+    pub timeout: Duration,
+}
+
+impl ScriptResolver {
+    pub fn new(f: impl Fn(&str) -> Option<PathBuf> + Send + Sync + 'static) -> Self {
+        Self {
+            resolve_fn: Arc::new(f),
+            timeout: Duration::from_secs(10),
+        }
+    }
+
+    pub fn convention(pattern: impl Into<String>) -> Self {
+        let pattern = pattern.into();
+        Self::new(move |id| Some(pattern.replace("{id}", id).into()))
+    }
+
+    pub fn manifest(paths: impl IntoIterator<Item = (String, PathBuf)>) -> Self {
+        let paths: HashMap<_, _> = paths.into_iter().collect();
+        Self::new(move |id| paths.get(id).cloned())
+    }
+
+    pub fn with_timeout(mut self, timeout: Duration) -> Self {
+        self.timeout = timeout;
+        self
+    }
+
+    pub fn path(&self, id: &str) -> Option<PathBuf> {
+        (self.resolve_fn)(id)
+    }
+}
+
+#[derive(Event)]
+pub struct UnresolvedScript {
+    pub entity: Entity,
+    pub id: String,
+}
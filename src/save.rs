@@ -0,0 +1,151 @@
+use crate::{Registry, ScopeData, ScriptBundle, ScriptInstance, TimedOperations};
+use bevy::ecs::{component::Component, entity::Entity, world::World};
+use serde::{Deserialize, Serialize};
+use serde_json::Value;
+use std::collections::{HashMap, HashSet};
+
+#[derive(Component, Default)]
+pub struct FiredEvents(pub HashSet<String>);
+
+#[derive(Clone, Debug, Default, Serialize, Deserialize)]
+pub struct SavedState {
+    pub dependencies: HashMap<String, f64>,
+    pub fired: Vec<String>,
+    pub added: HashMap<String, Value>,
+    #[serde(default)]
+    pub removed: Vec<String>,
+    #[serde(default)]
+    pub timers: TimedOperations,
+}
+
+#[derive(Clone, Debug, Serialize, Deserialize)]
+pub struct SavedScript {
+    pub id: String,
+    pub params: HashMap<String, Value>,
+    pub overrides: HashMap<String, Value>,
+    pub state: SavedState,
+}
+
+impl Registry {
+    pub fn save(&self, world: &World, entity: Entity) -> Option<SavedScript> {
+        let instance = world.get::<ScriptInstance>(entity)?;
+
+        // Components added or changed after spawning, such as by operations, are kept so they
+        // don't depend on those operations running again.
+        let mut snapshot = self.snapshot(world, entity).0;
+        // Children are spawned again by the script.
+        snapshot.remove("children");
+        let mut removed: Vec<_> = instance
+            .data
+            .0
+            .keys()
+            .filter(|id| self.snapshot_fns.contains_key(*id) && !snapshot.contains_key(*id))
+            .cloned()
+            .collect();
+        removed.sort();
+
+        let added = snapshot
+            .into_iter()
+            .filter(|(id, value)| {
+                instance
+                    .data
+                    .0
+                    .get(id)
+                    .map_or(true, |spawned| !is_same(spawned, value))
+            })
+            .collect();
+
+        let dependencies = world
+            .get::<ScopeData>(entity)
+            .map(|scope_data| {
+                scope_data
+                    .dependencies
+                    .iter()
+                    .filter_map(|(id, value)| value.map(|value| (id.clone(), value)))
+                    .collect()
+            })
+            .unwrap_or_default();
+
+        let mut fired: Vec<_> = world
+            .get::<FiredEvents>(entity)
+            .map(|fired| fired.0.iter().cloned().collect())
+            .unwrap_or_default();
+        fired.sort();
+
+        Some(SavedScript {
+            id: instance.id.clone(),
+            params: instance.params.clone(),
+            overrides: instance.overrides.clone(),
+            state: SavedState {
+                dependencies,
+                fired,
+                added,
+                removed,
+                timers: world
+                    .get::<TimedOperations>(entity)
+                    .cloned()
+                    .unwrap_or_default(),
+            },
+        })
+    }
+}
+
+// Snapshots write numbers as floats, so `2` and `2.0` are the same value.
+fn is_same(a: &Value, b: &Value) -> bool {
+    match (a, b) {
+        (Value::Number(a), Value::Number(b)) => a.as_f64() == b.as_f64(),
+        (Value::Array(a), Value::Array(b)) => {
+            a.len() == b.len() && a.iter().zip(b).all(|(a, b)| is_same(a, b))
+        }
+        (Value::Object(a), Value::Object(b)) => {
+            a.len() == b.len()
+                && a.iter()
+                    .all(|(key, a)| b.get(key).is_some_and(|b| is_same(a, b)))
+        }
+        (a, b) => a == b,
+    }
+}
+
+impl ScriptBundle {
+    pub fn restore(saved: SavedScript) -> Self {
+        let mut bundle = Self::with_params(saved.id, saved.params).with_overrides(saved.overrides);
+        bundle.state = Some(saved.state);
+        bundle
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use serde_json::json;
+
+    #[test]
+    fn round_trip() {
+        let state = json!({
+            "dependencies": {"damage": 2.0},
+            "fired": ["on_equip"],
+            "added": {"invincible": null},
+            "removed": ["durability"],
+            "timers": [{
+                "kind": {"every": 2.0},
+                "remaining": 1.5,
+                "operations": {"add": "invincible"},
+                "trigger": "on_equip",
+            }],
+        });
+
+        let saved: SavedState = serde_json::from_value(state.clone()).unwrap();
+        assert_eq!(saved.removed, ["durability"]);
+        assert!(!saved.timers.is_empty());
+        assert_eq!(serde_json::to_value(&saved).unwrap(), state);
+    }
+
+    #[test]
+    fn older_saves() {
+        let state = json!({"dependencies": {}, "fired": [], "added": {}});
+
+        let saved: SavedState = serde_json::from_value(state).unwrap();
+        assert!(saved.removed.is_empty());
+        assert!(saved.timers.is_empty());
+    }
+}
@@ -1,32 +1,73 @@
-use super::expr::{Expr, StaticExpr};
+use super::expr::{Expr, ExprData, StaticExpr};
 use crate::{Depends, Registry};
-use bevy::ecs::{component::Component, system::EntityCommands};
-use std::{collections::HashMap, marker::PhantomData};
+use bevy::ecs::{component::Component, system::EntityCommands, world::EntityWorldMut};
+use std::{any::TypeId, collections::HashMap, marker::PhantomData};
 
 #[derive(Component)]
 pub struct Scope<T> {
     pub _marker: PhantomData<T>,
 }
 
+impl<T: Component> Scope<T> {
+    pub fn remove(entity_commands: &mut EntityCommands) {
+        entity_commands
+            .add(|mut entity: EntityWorldMut| {
+                if let Some(mut scope_data) = entity.get_mut::<ScopeData>() {
+                    scope_data.exprs.remove(&TypeId::of::<T>());
+                    scope_data.sources.remove(&TypeId::of::<T>());
+                }
+            })
+            .remove::<Scope<T>>();
+    }
+}
+
+// Holds every derived expression on the entity, since overrides can add a derived
+// value on top of the ones in the script and a second `ScopeData` would replace the first.
 #[derive(Component)]
 pub struct ScopeData {
-    pub(crate) expr: Expr,
+    pub(crate) exprs: HashMap<TypeId, Expr>,
+    // What each expression was built from, so saves keep the expression rather than its result.
+    pub(crate) sources: HashMap<TypeId, ExprData>,
     pub(crate) dependencies: HashMap<String, Option<f64>>,
 }
 
 impl ScopeData {
+    pub fn new<T: Component>(expr: Expr) -> Self {
+        let dependencies = expr.deps().into_iter().map(|id| (id, None)).collect();
+        Self {
+            exprs: HashMap::from([(TypeId::of::<T>(), expr)]),
+            sources: HashMap::new(),
+            dependencies,
+        }
+    }
+
     pub fn spawn<T: Component>(self, registry: &Registry, entity_commands: &mut EntityCommands) {
         for id in self.dependencies.keys() {
             let dep = registry.deps.get(id).unwrap();
             dep.spawn(id.clone(), entity_commands);
         }
 
-        entity_commands.insert((
-            self,
-            Scope {
+        // Each entity has a single scope shared by all of its expressions.
+        entity_commands
+            .add(move |mut entity: EntityWorldMut| {
+                if let Some(mut scope_data) = entity.get_mut::<ScopeData>() {
+                    scope_data.extend(self);
+                } else {
+                    entity.insert(self);
+                }
+            })
+            .insert(Scope {
                 _marker: PhantomData::<T>,
-            },
-        ));
+            });
+    }
+
+    pub fn extend(&mut self, other: ScopeData) {
+        self.exprs.extend(other.exprs);
+        self.sources.extend(other.sources);
+
+        for (id, value) in other.dependencies {
+            self.dependencies.entry(id).or_insert(value);
+        }
     }
 
     pub fn set_dependency(&mut self, id: &str, value: f64) {
@@ -35,17 +76,29 @@ impl ScopeData {
         }
     }
 
-    pub fn run(&self) -> Option<StaticExpr> {
-        if !self.dependencies.values().all(Option::is_some) {
+    pub fn source<T: Component>(&self) -> Option<&ExprData> {
+        self.sources.get(&TypeId::of::<T>())
+    }
+
+    pub fn run<T: Component>(&self) -> Option<StaticExpr> {
+        let expr = self.exprs.get(&TypeId::of::<T>())?;
+
+        let is_ready = expr
+            .deps()
+            .iter()
+            .all(|id| matches!(self.dependencies.get(id), Some(Some(_))));
+        if !is_ready {
             return None;
         }
 
-        Some(self.expr.run(self))
+        Some(expr.run(self))
     }
 }
 
 pub(crate) trait Dependency: Send + Sync + 'static {
     fn spawn(&self, id: String, entity_commands: &mut EntityCommands);
+
+    fn remove(&self, entity_commands: &mut EntityCommands);
 }
 
 impl<C: Component> Dependency for PhantomData<C> {
@@ -55,4 +108,8 @@ impl<C: Component> Dependency for PhantomData<C> {
             _marker: PhantomData::<C>,
         });
     }
+
+    fn remove(&self, entity_commands: &mut EntityCommands) {
+        entity_commands.remove::<Depends<C>>();
+    }
 }
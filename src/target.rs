@@ -0,0 +1,212 @@
+use crate::{
+    capability::parse, Capabilities, CapabilityError, Register, Registry, ScriptComponent,
+    ScriptInstance,
+};
+use bevy::{
+    asset::AssetServer,
+    ecs::{
+        component::Component,
+        entity::Entity,
+        query::With,
+        system::{CommandQueue, Commands, EntityCommands},
+        world::{EntityWorldMut, Mut, World},
+    },
+    hierarchy::{Children, Parent},
+    log::error,
+    transform::components::GlobalTransform,
+};
+use serde::{Deserialize, Serialize};
+use serde_json::Value;
+use std::collections::{BTreeSet, HashMap};
+
+#[derive(Clone, Debug, Default, Component, Serialize, Deserialize)]
+#[serde(transparent)]
+pub struct Tags(pub BTreeSet<String>);
+
+impl Tags {
+    pub fn contains(&self, tag: &str) -> bool {
+        self.0.contains(tag)
+    }
+}
+
+impl ScriptComponent for Tags {
+    type Data = Tags;
+
+    fn snapshot(&self) -> Option<Value> {
+        serde_json::to_value(self).ok()
+    }
+}
+
+impl Register for Tags {
+    fn register<C: Component>(
+        self,
+        _registry: &Registry,
+        _asset_server: &AssetServer,
+        entity_commands: &mut EntityCommands,
+    ) {
+        entity_commands.add(move |mut entity: EntityWorldMut| {
+            if let Some(mut current) = entity.get_mut::<Tags>() {
+                current.0.extend(self.0);
+            } else {
+                entity.insert(self);
+            }
+        });
+    }
+}
+
+// The entity that caused a scripted entity's event, such as whoever equipped an item.
+// Scripts can't know this, so the game inserts it along with the event component, and the
+// `trigger` target is empty without it.
+#[derive(Clone, Copy, Debug, Component)]
+pub struct TriggeredBy(pub Entity);
+
+#[derive(Clone, Copy, Debug, Deserialize)]
+#[serde(rename_all = "snake_case")]
+enum TargetName {
+    #[serde(rename = "self")]
+    Entity,
+    Parent,
+    Children,
+    Trigger,
+}
+
+// A typo in a query would otherwise target every scripted entity.
+#[derive(Clone, Debug, Deserialize)]
+#[serde(deny_unknown_fields)]
+struct TargetQuery {
+    tag: Option<String>,
+    radius: Option<f32>,
+}
+
+#[derive(Clone, Debug, Deserialize)]
+#[serde(untagged)]
+enum Target {
+    Named(TargetName),
+    Query(TargetQuery),
+}
+
+impl Target {
+    // The capabilities needed to target these entities.
+    fn names(&self) -> Vec<&'static str> {
+        match self {
+            Target::Named(TargetName::Entity) => Vec::new(),
+            Target::Named(TargetName::Parent) => vec!["parent"],
+            Target::Named(TargetName::Children) => vec!["children"],
+            Target::Named(TargetName::Trigger) => vec!["trigger"],
+            Target::Query(TargetQuery {
+                tag: None,
+                radius: None,
+            }) => vec!["all"],
+            Target::Query(TargetQuery { tag, radius }) => {
+                let mut names = Vec::new();
+                if tag.is_some() {
+                    names.push("tag");
+                }
+                if radius.is_some() {
+                    names.push("radius");
+                }
+                names
+            }
+        }
+    }
+
+    fn resolve(&self, world: &mut World, entity: Entity) -> Vec<Entity> {
+        let Some(entity_ref) = world.get_entity(entity) else {
+            return Vec::new();
+        };
+
+        match self {
+            Target::Named(TargetName::Entity) => vec![entity],
+            Target::Named(TargetName::Parent) => entity_ref
+                .get::<Parent>()
+                .map(|parent| vec![parent.get()])
+                .unwrap_or_default(),
+            Target::Named(TargetName::Children) => entity_ref
+                .get::<Children>()
+                .map(|children| children.to_vec())
+                .unwrap_or_default(),
+            Target::Named(TargetName::Trigger) => entity_ref
+                .get::<TriggeredBy>()
+                .map(|trigger| vec![trigger.0])
+                .unwrap_or_default(),
+            Target::Query(TargetQuery { tag, radius }) => {
+                let origin = entity_ref
+                    .get::<GlobalTransform>()
+                    .map(|transform| transform.translation());
+
+                let mut query = world.query_filtered::<(
+                    Entity,
+                    Option<&Tags>,
+                    Option<&GlobalTransform>,
+                ), With<ScriptInstance>>();
+
+                query
+                    .iter(world)
+                    .filter(|(_, tags, _)| {
+                        tag.as_ref()
+                            .map_or(true, |tag| tags.is_some_and(|tags| tags.contains(tag)))
+                    })
+                    .filter(|(other, _, transform)| {
+                        // Radius targets are the other entities around this one.
+                        radius.map_or(true, |radius| {
+                            *other != entity
+                                && origin.zip(*transform).is_some_and(|(origin, transform)| {
+                                    origin.distance(transform.translation()) <= radius
+                                })
+                        })
+                    })
+                    .map(|(other, _, _)| other)
+                    .collect()
+            }
+        }
+    }
+}
+
+pub(crate) fn run_operations(
+    entity_commands: &mut EntityCommands,
+    target: Value,
+    operations: HashMap<String, Value>,
+    is_revert: bool,
+) {
+    let target: Target = match serde_json::from_value(target) {
+        Ok(target) => target,
+        Err(error) => {
+            error!("Failed to read operation target: {error}");
+            return;
+        }
+    };
+
+    entity_commands.add(move |entity: Entity, world: &mut World| {
+        let targets = target.resolve(world, entity);
+
+        let mut queue = CommandQueue::default();
+        world.resource_scope(|world, registry: Mut<Registry>| {
+            let asset_server = world.resource::<AssetServer>().clone();
+            let mut commands = Commands::new(&mut queue, world);
+
+            for target in targets {
+                let mut entity_commands = commands.entity(target);
+                if is_revert {
+                    registry.revert_operations(&mut entity_commands, operations.clone());
+                } else {
+                    registry.run_operations(
+                        &asset_server,
+                        &mut entity_commands,
+                        operations.clone(),
+                    );
+                }
+            }
+        });
+        queue.apply(world);
+    });
+}
+
+pub(crate) fn check(value: &Value, capabilities: &Capabilities) -> Result<(), CapabilityError> {
+    match parse::<Target>(value)? {
+        Some(target) => target
+            .names()
+            .into_iter()
+            .try_for_each(|name| capabilities.check_target(name)),
+        None => Ok(()),
+    }
+}
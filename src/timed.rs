@@ -0,0 +1,259 @@
+use crate::{capability::parse, Capabilities, CapabilityError, Operation, Registry};
+use bevy::{
+    asset::AssetServer,
+    ecs::{
+        component::Component,
+        entity::Entity,
+        removal_detection::RemovedComponents,
+        system::{Commands, EntityCommands, Query, Res},
+        world::EntityWorldMut,
+    },
+    time::Time,
+};
+use serde::{Deserialize, Serialize};
+use serde_json::Value;
+use std::{any::TypeId, collections::HashMap};
+
+#[derive(Clone, Copy, Debug, PartialEq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+enum TimedKind {
+    After,
+    Every(f32),
+    For,
+}
+
+#[derive(Clone, Debug, Serialize, Deserialize)]
+struct TimedOperation {
+    kind: TimedKind,
+    remaining: f32,
+    operations: HashMap<String, Value>,
+    trigger: Option<String>,
+}
+
+// Operations waiting on a timer, cancelled when their trigger is removed from the entity.
+#[derive(Clone, Debug, Default, Component, Serialize, Deserialize)]
+#[serde(transparent)]
+pub struct TimedOperations(Vec<TimedOperation>);
+
+impl TimedOperations {
+    pub fn is_empty(&self) -> bool {
+        self.0.is_empty()
+    }
+
+    fn push(
+        entity_commands: &mut EntityCommands,
+        kind: TimedKind,
+        seconds: f32,
+        operations: HashMap<String, Value>,
+    ) {
+        let timed = TimedOperation {
+            kind,
+            remaining: seconds,
+            operations,
+            trigger: None,
+        };
+
+        entity_commands.add(move |mut entity: EntityWorldMut| {
+            if let Some(mut current) = entity.get_mut::<TimedOperations>() {
+                current.0.push(timed);
+            } else {
+                entity.insert(TimedOperations(vec![timed]));
+            }
+        });
+    }
+
+    pub(crate) fn restore(entity: &mut EntityWorldMut, timers: TimedOperations) {
+        if timers.is_empty() {
+            return;
+        }
+
+        if let Some(mut current) = entity.get_mut::<TimedOperations>() {
+            current.0.extend(timers.0);
+        } else {
+            entity.insert(timers);
+        }
+    }
+
+    // Undoes effects that only last a while, for entities losing their timers early.
+    pub(crate) fn revert(&self, registry: &Registry, entity_commands: &mut EntityCommands) {
+        for timed in self.0.iter().filter(|timed| timed.kind == TimedKind::For) {
+            registry.revert_operations(entity_commands, timed.operations.clone());
+        }
+    }
+
+    // Operations don't know which event ran them, so timers are tagged after the fact.
+    pub(crate) fn set_trigger(entity_commands: &mut EntityCommands, trigger: String) {
+        entity_commands.add(move |mut entity: EntityWorldMut| {
+            if let Some(mut current) = entity.get_mut::<TimedOperations>() {
+                for timed in current.0.iter_mut().filter(|timed| timed.trigger.is_none()) {
+                    timed.trigger = Some(trigger.clone());
+                }
+            }
+        });
+    }
+}
+
+#[derive(Deserialize)]
+struct TimedData(f32, HashMap<String, Value>);
+
+fn check_timed(
+    registry: &Registry,
+    value: &Value,
+    capabilities: &Capabilities,
+) -> Result<(), CapabilityError> {
+    parse::<TimedData>(value)?;
+    match value.get(1) {
+        Some(operations) => registry.check_operations(operations, capabilities),
+        None => Ok(()),
+    }
+}
+
+pub struct AfterOperation;
+
+impl Operation for AfterOperation {
+    fn spawn(
+        &self,
+        _registry: &Registry,
+        _asset_server: &AssetServer,
+        entity_commands: &mut EntityCommands,
+        value: Value,
+    ) {
+        let TimedData(delay, operations) = serde_json::from_value(value).unwrap();
+        TimedOperations::push(entity_commands, TimedKind::After, delay, operations);
+    }
+
+    fn check(
+        &self,
+        registry: &Registry,
+        value: &Value,
+        capabilities: &Capabilities,
+    ) -> Result<(), CapabilityError> {
+        check_timed(registry, value, capabilities)
+    }
+}
+
+pub struct EveryOperation;
+
+impl Operation for EveryOperation {
+    fn spawn(
+        &self,
+        _registry: &Registry,
+        _asset_server: &AssetServer,
+        entity_commands: &mut EntityCommands,
+        value: Value,
+    ) {
+        let TimedData(interval, operations) = serde_json::from_value(value).unwrap();
+        TimedOperations::push(
+            entity_commands,
+            TimedKind::Every(interval),
+            interval,
+            operations,
+        );
+    }
+
+    fn check(
+        &self,
+        registry: &Registry,
+        value: &Value,
+        capabilities: &Capabilities,
+    ) -> Result<(), CapabilityError> {
+        check_timed(registry, value, capabilities)
+    }
+}
+
+pub struct ForOperation;
+
+impl Operation for ForOperation {
+    fn spawn(
+        &self,
+        registry: &Registry,
+        asset_server: &AssetServer,
+        entity_commands: &mut EntityCommands,
+        value: Value,
+    ) {
+        let TimedData(duration, operations) = serde_json::from_value(value).unwrap();
+        registry.run_operations(asset_server, entity_commands, operations.clone());
+        TimedOperations::push(entity_commands, TimedKind::For, duration, operations);
+    }
+
+    fn check(
+        &self,
+        registry: &Registry,
+        value: &Value,
+        capabilities: &Capabilities,
+    ) -> Result<(), CapabilityError> {
+        check_timed(registry, value, capabilities)
+    }
+}
+
+pub(crate) fn run_timed_operations(
+    mut commands: Commands,
+    registry: Res<Registry>,
+    asset_server: Res<AssetServer>,
+    time: Res<Time>,
+    mut query: Query<(Entity, &mut TimedOperations)>,
+) {
+    let delta = time.delta_seconds();
+
+    for (entity, mut current) in &mut query {
+        let mut entity_commands = commands.entity(entity);
+
+        current.0.retain_mut(|timed| {
+            timed.remaining -= delta;
+            if timed.remaining > 0. {
+                return true;
+            }
+
+            match timed.kind {
+                TimedKind::After | TimedKind::Every(_) => {
+                    registry.run_operations(
+                        &asset_server,
+                        &mut entity_commands,
+                        timed.operations.clone(),
+                    );
+                    if let Some(trigger) = &timed.trigger {
+                        TimedOperations::set_trigger(&mut entity_commands, trigger.clone());
+                    }
+                }
+                TimedKind::For => {
+                    registry.revert_operations(&mut entity_commands, timed.operations.clone());
+                }
+            }
+
+            if let TimedKind::Every(interval) = timed.kind {
+                timed.remaining += interval;
+                true
+            } else {
+                false
+            }
+        });
+    }
+}
+
+pub(crate) fn cancel_timed_operations<C: Component>(
+    mut commands: Commands,
+    registry: Res<Registry>,
+    mut removed: RemovedComponents<C>,
+    mut query: Query<&mut TimedOperations>,
+) {
+    let trigger = &registry.events[&TypeId::of::<C>()];
+
+    for entity in removed.read() {
+        let Ok(mut current) = query.get_mut(entity) else {
+            continue;
+        };
+        let mut entity_commands = commands.entity(entity);
+
+        current.0.retain(|timed| {
+            if timed.trigger.as_ref() != Some(trigger) {
+                return true;
+            }
+
+            // Effects that only last a while end with their trigger.
+            if timed.kind == TimedKind::For {
+                registry.revert_operations(&mut entity_commands, timed.operations.clone());
+            }
+            false
+        });
+    }
+}